@@ -1,17 +1,33 @@
 mod models;
-use std::fs;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use models::parser::MyParser;
 
-use crate::models::{controls::ID3_GENRES, track::Track};
+use crate::models::{
+    artwork::{self, ExtractArt},
+    controls::{AppConfig, ChangeSet, JournalMode, ModifyAction, ID3_GENRES},
+    diff,
+    id3v1::Trailer,
+    journal::{self, Journal},
+    manifest,
+    output::{self, OutputFormat},
+    table,
+    template::Template,
+    track::Track,
+    walker::{self, Walker},
+};
 
 fn print_genres() {
     println!("{0: <25} | {1: <25}", "ID: Genre", "ID: Genre");
     println!("{:-<26}|{:-<26}", "", "");
 
-    let offset = (ID3_GENRES.len() + 1) / 2;
+    let offset = ID3_GENRES.len().div_ceil(2);
     for i in 0..offset {
-        let left = format!("{}", ID3_GENRES[i]);
+        let left = ID3_GENRES[i].to_string();
         let right = if i + offset < ID3_GENRES.len() {
             format!("{}: {}", i + offset, ID3_GENRES[i + offset])
         } else {
@@ -30,42 +46,299 @@ fn main() {
         return;
     }
 
-    let mut files = Vec::new();
-    if config.recursive && config.target_path.is_dir() {
-        if let Ok(entries) = fs::read_dir(&config.target_path) {
-            for entry in entries.flatten() {
-                if entry.path().extension().map_or(false, |e| e == "mp3") {
-                    files.push(entry.path());
-                }
+    if let Some(target) = &config.undo {
+        let Some(path) = target.clone().or_else(journal::latest) else {
+            eprintln!("No journal found to undo");
+            return;
+        };
+        println!("Undoing {:?}", path);
+        if let Err(e) = journal::undo(&path) {
+            eprintln!("{}", e);
+        }
+        return;
+    }
+
+    if let Some(csv_path) = &config.export_csv {
+        if let Err(e) = table::export(csv_path, &config.csv_fields, &target_files(&config)) {
+            eprintln!("{}", e);
+        }
+        return;
+    }
+
+    let mut journal = match &config.journal {
+        _ if change_set.dry_run => None,
+        JournalMode::Off => None,
+        JournalMode::File(path) => Some(Journal::new(path.clone())),
+        JournalMode::Auto => match Journal::for_this_run() {
+            Ok(j) => Some(j),
+            Err(e) => {
+                eprintln!("{}, changes can't be undone", e);
+                None
+            }
+        },
+    };
+
+    let jobs: Vec<_> = if let Some(csv_path) = &config.import_csv {
+        match table::load(csv_path) {
+            Ok(rows) => rows
+                .into_iter()
+                .map(|row| (row.path, change_set.clone(), Some(row.cells)))
+                .collect(),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
             }
         }
-    } else if !config.recursive && config.target_path.is_dir() {
-        eprintln!("Invalid file, use 'editag --help' for help");
+    } else if let Some(manifest) = &config.import {
+        match manifest::load(manifest) {
+            Ok(files) => files
+                .into_iter()
+                .map(|(path, mut actions)| {
+                    let mut file_changes = change_set.clone();
+                    actions.append(&mut file_changes.actions);
+                    file_changes.actions = actions;
+                    (path, file_changes, None)
+                })
+                .collect(),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+    } else {
+        target_files(&config)
+            .into_iter()
+            .map(|path| (path, change_set.clone(), None))
+            .collect()
+    };
+
+    let jobs = if change_set.auto_number {
+        auto_number(jobs)
     } else {
-        files.push(config.target_path.clone());
+        jobs
+    };
+    let jobs = if change_set.extract_art == Some(ExtractArt::Album) {
+        album_art(jobs, change_set.dry_run)
+    } else {
+        jobs
+    };
+
+    let json_array = change_set.output == OutputFormat::Json
+        && (config.recursive || config.import.is_some() || config.import_csv.is_some());
+    if change_set.output != OutputFormat::Table {
+        output::send_status_to_stderr();
+    }
+    if json_array {
+        output::begin_array();
     }
 
-    for file_path in files {
-        match Track::load(file_path) {
-            Ok(mut track) => match track.apply(&change_set, config.version) {
-                Ok(modified) => {
-                    if modified {
-                        if let Err(e) = track.save(config.version) {
-                            eprintln!("Failed the save {:?}: {}", track.path, e)
-                        }
-                    }
-                    if change_set.format_file {
-                        if let Err(e) = track.format_filename() {
-                            eprintln!("Failed to format filename {:?}: {}", track.path, e);
-                        };
-                    }
-                    println!("Processed: {:?}\n", track.path)
+    let mut claimed = HashSet::new();
+    for (file_path, mut change_set, cells) in jobs {
+        let track = match Track::load(file_path) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("Error loading file: {}", e);
+                continue;
+            }
+        };
+        if let Some(cells) = cells {
+            match table::changed_actions(&track, &cells) {
+                Ok(mut actions) => {
+                    actions.append(&mut change_set.actions);
+                    change_set.actions = actions;
                 }
                 Err(e) => {
-                    eprintln!("{}", e)
+                    eprintln!("{}", e);
+                    continue;
+                }
+            }
+            if change_set.is_empty() {
+                status!("Unchanged: {:?}\n", track.path);
+                continue;
+            }
+        }
+        process(track, &change_set, &config, &mut journal, &mut claimed);
+    }
+
+    if json_array {
+        output::end_array();
+    }
+
+    if let Some(journal) = journal.filter(|j| !j.is_empty()) {
+        status!(
+            "Changes recorded in {:?}, use 'editag --undo' to revert them",
+            journal.path()
+        );
+    }
+}
+
+fn process(
+    mut track: Track,
+    change_set: &ChangeSet,
+    config: &AppConfig,
+    journal: &mut Option<Journal>,
+    claimed: &mut HashSet<PathBuf>,
+) {
+    let before = track.tag.clone();
+    let version = config.version.resolve(track.original_version);
+    match track.apply(change_set, version) {
+        Ok(_) if change_set.dry_run => {
+            let rename = match &change_set.format_file {
+                Some(template) => match track.formatted_path(template) {
+                    Ok(path) => Some(path).filter(|p| *p != track.path),
+                    Err(e) => {
+                        eprintln!("Can't format filename {:?}: {}", track.path, e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            diff::print_diff(
+                &track.path,
+                &diff::as_written(&before, track.original_version),
+                &diff::as_written(&track.tag, Some(version)),
+                track.id3v1.as_deref(),
+                &track.trailer,
+                rename.as_deref(),
+            );
+            if let Some(organize) = &change_set.organize {
+                if let Err(e) = organize.run(&mut track, claimed, journal, true) {
+                    eprintln!("Can't organize {:?}: {}", track.path, e);
                 }
-            },
-            Err(e) => eprintln!("Error loading file: {}", e),
+            }
+            status!()
+        }
+        Ok(modified) => {
+            if modified {
+                save(&track, &before, version, journal, config);
+            }
+            if let Some(template) = &change_set.format_file {
+                format_filename(&mut track, template, journal);
+            }
+            if let Some(organize) = &change_set.organize {
+                if let Err(e) = organize.run(&mut track, claimed, journal, false) {
+                    eprintln!("Failed to organize {:?}: {}", track.path, e);
+                }
+            }
+            status!("Processed: {:?}\n", track.path)
+        }
+        Err(e) => {
+            eprintln!("{}", e)
+        }
+    }
+}
+
+/// Numbers the files of each directory in natural file name order, setting the directory's file count
+/// as the total.
+fn auto_number<T>(mut jobs: Vec<(PathBuf, ChangeSet, T)>) -> Vec<(PathBuf, ChangeSet, T)> {
+    let mut dirs: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for (path, _, _) in &jobs {
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        dirs.entry(dir).or_default().push(path.clone());
+    }
+    let name = |p: &PathBuf| {
+        p.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    for files in dirs.values_mut() {
+        files.sort_by(|a, b| walker::natural_cmp(&name(a), &name(b)));
+    }
+
+    for (path, change_set, _) in &mut jobs {
+        let files = &dirs[path.parent().unwrap_or(Path::new(""))];
+        let n = files.iter().position(|f| f == path).unwrap_or_default() + 1;
+        change_set.actions.push(ModifyAction::TrackNumber(n as u32));
+        change_set
+            .actions
+            .push(ModifyAction::TotalTracks(Some(files.len() as u32)));
+    }
+    jobs
+}
+
+/// Extracts the covers shared by whole directories. Their tracks still extract their other
+/// pictures, while the tracks of the other directories extract all of theirs.
+fn album_art<T>(jobs: Vec<(PathBuf, ChangeSet, T)>, dry_run: bool) -> Vec<(PathBuf, ChangeSet, T)> {
+    let paths: Vec<_> = jobs.iter().map(|(path, _, _)| path.clone()).collect();
+    let done = artwork::extract_album_covers(&paths, dry_run);
+    jobs.into_iter()
+        .map(|(path, mut change_set, cells)| {
+            if !done.contains(path.parent().unwrap_or(Path::new(""))) {
+                change_set.extract_art = Some(ExtractArt::Tracks);
+            }
+            (path, change_set, cells)
+        })
+        .collect()
+}
+
+/// The files named on the command line, or every mp3 below it with -r.
+fn target_files(config: &AppConfig) -> Vec<PathBuf> {
+    if config.recursive && config.target_path.is_dir() {
+        Walker::new(config).collect()
+    } else if !config.recursive && config.target_path.is_dir() {
+        eprintln!("Invalid file, use 'editag --help' for help");
+        Vec::new()
+    } else {
+        vec![config.target_path.clone()]
+    }
+}
+
+/// Records the tag as it was before this run, then saves the new one. Nothing is saved if the
+/// previous tag can't be recorded, as the change couldn't be undone. A file without an ID3v2 tag
+/// only gets one when the tag itself was changed, not just its ID3v1 tag.
+fn save(
+    track: &Track,
+    before: &id3::Tag,
+    version: id3::Version,
+    journal: &mut Option<Journal>,
+    config: &AppConfig,
+) {
+    let trailer_only = track.original_version.is_none() && track.tag == *before;
+    if let Some(journal) = journal {
+        let recorded = match trailer_only {
+            true => Ok(()),
+            false => journal.record_tag(&track.path, before, track.original_version),
+        };
+        if let Err(e) = recorded {
+            eprintln!("Failed the save {:?}: {}", track.path, e);
+            return;
+        }
+        if track.trailer != Trailer::Keep {
+            if let Err(e) = journal.record_trailer(&track.path, track.id3v1.as_deref()) {
+                eprintln!("Failed the save {:?}: {}", track.path, e);
+                return;
+            }
+        }
+    }
+    let saved = if trailer_only {
+        track.save_trailer(&config.backup)
+    } else {
+        track.save(version, &config.backup)
+    };
+    if let Err(e) = saved {
+        eprintln!("Failed the save {:?}: {}", track.path, e)
+    }
+}
+
+/// Renames the file, recording it once it succeeded so a refused rename is never undone.
+fn format_filename(track: &mut Track, template: &Template, journal: &mut Option<Journal>) {
+    let new_path = match track.formatted_path(template) {
+        Ok(path) if path == track.path => return,
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Failed to format filename {:?}: {}", track.path, e);
+            return;
+        }
+    };
+    let old_path = track.path.clone();
+    if let Err(e) = track.rename(new_path) {
+        eprintln!("Failed to format filename {:?}: {}", track.path, e);
+        return;
+    }
+    if let Some(journal) = journal {
+        if let Err(e) = journal.record_rename(&old_path, &track.path) {
+            eprintln!("Failed to record rename of {:?}: {}", old_path, e);
         }
     }
 }
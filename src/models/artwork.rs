@@ -0,0 +1,92 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use id3::frame::{Picture, PictureType};
+
+use crate::status;
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExtractArt {
+    /// Every picture next to its track, named after the track and the picture type.
+    Tracks,
+    /// A single `cover.<ext>` per directory whose tracks share the same front cover, and the
+    /// other pictures next to each track. Other directories fall back to `tracks`.
+    Album,
+}
+
+/// The file extension for a picture's MIME type. ID3v2.2 tags give a format like `JPG` instead.
+pub fn extension(mime_type: &str) -> &'static str {
+    let mime_type = mime_type.trim().to_ascii_lowercase();
+    match mime_type.strip_prefix("image/").unwrap_or(&mime_type) {
+        "jpeg" | "jpg" | "pjpeg" => "jpg",
+        "png" => "png",
+        "gif" => "gif",
+        "webp" => "webp",
+        "bmp" | "x-ms-bmp" => "bmp",
+        "tiff" => "tiff",
+        _ => "bin",
+    }
+}
+
+/// Writes the front cover shared by every track of a directory to `cover.<ext>` in it. Returns
+/// the directories that were taken care of, whose tracks don't need their front cover extracted.
+pub fn extract_album_covers(tracks: &[PathBuf], dry_run: bool) -> HashSet<PathBuf> {
+    let mut dirs: BTreeMap<PathBuf, Vec<&PathBuf>> = BTreeMap::new();
+    for path in tracks {
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        dirs.entry(dir).or_default().push(path);
+    }
+
+    let mut done = HashSet::new();
+    for (dir, paths) in dirs {
+        let covers: Vec<_> = paths.iter().map(|p| front_cover(p)).collect();
+        let Some(Some(cover)) = covers.first() else {
+            continue;
+        };
+        if covers
+            .iter()
+            .any(|c| c.as_ref().is_none_or(|c| c.data != cover.data))
+        {
+            status!(
+                "Tracks in {:?} have different covers, extracting them per track",
+                dir
+            );
+            continue;
+        }
+
+        let target = dir.join(format!("cover.{}", extension(&cover.mime_type)));
+        match fs::read(&target) {
+            Ok(existing) if existing == cover.data => {
+                status!("{:?} is already up to date", target);
+            }
+            Ok(_) => {
+                status!(
+                    "{:?} already exists with another image, extracting per track",
+                    target
+                );
+                continue;
+            }
+            Err(_) if dry_run => status!("Would extract the album cover to {:?}", target),
+            Err(_) => match fs::write(&target, &cover.data) {
+                Ok(()) => status!("Extracted the album cover to {:?}", target),
+                Err(e) => {
+                    eprintln!("Error writing {:?} :: {}", target, e);
+                    continue;
+                }
+            },
+        }
+        done.insert(dir);
+    }
+    done
+}
+
+fn front_cover(path: &Path) -> Option<Picture> {
+    id3::Tag::read_from_path(path)
+        .ok()?
+        .pictures()
+        .find(|p| p.picture_type == PictureType::CoverFront)
+        .cloned()
+}
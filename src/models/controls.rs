@@ -1,31 +1,185 @@
-use id3::Version;
+use glob::Pattern;
+use id3::{frame::PictureType, Frame, Timestamp, Version};
+
+use crate::models::{
+    artwork::ExtractArt,
+    cover::{CoverOptions, PictureSelector},
+    filename::FilenamePattern,
+    organize::Organize,
+    output::OutputFormat,
+    template::Template,
+};
 use std::path::PathBuf;
 
+#[derive(Clone)]
 pub enum ModifyAction {
     Title(String),
     Album(String),
-    Artist(String),
+    Artist(Vec<String>),
     AlbumArtist(String),
-    Year(i32),
+    RecordingDate(Timestamp),
+    OriginalDate(Timestamp),
+    ReleaseDate(Timestamp),
     TrackNumber(u32),
-    Genre(String),
+    /// `None` removes the total, keeping the track number.
+    TotalTracks(Option<u32>),
+    DiscNumber(u32),
+    /// `None` removes the total, keeping the disc number.
+    TotalDiscs(Option<u32>),
+    Genre(Vec<String>),
+    /// Decodes `(17)` style references and fixes the spelling of known genres.
+    NormalizeGenre,
+    /// Adds a value to a multi-value text frame unless it is already there.
+    AddValue {
+        id: String,
+        value: String,
+    },
+    RemoveValue {
+        id: String,
+        value: String,
+    },
+    /// Splits the artists and genres on any of the separators.
+    SplitValues(Vec<String>),
+    /// Sets the front cover.
     CoverArt(PathBuf),
+    /// Adds a picture, replacing the one of the same type.
+    Picture {
+        picture_type: PictureType,
+        description: String,
+        path: PathBuf,
+    },
+    RemovePictures(Vec<PictureSelector>),
     DeleteTag(String),
-    Custom(String, String),
+    Custom(Frame),
+    Comment {
+        lang: String,
+        description: String,
+        text: String,
+    },
+    RemoveComment {
+        lang: Option<String>,
+        description: String,
+    },
+    Lyrics {
+        lang: String,
+        description: String,
+        text: String,
+    },
+    SyncedLyrics {
+        lang: String,
+        description: String,
+        content: Vec<(u32, String)>,
+    },
+    AutoLrc {
+        lang: String,
+        description: String,
+    },
+    UserText(String, String),
+    UserLink(String, String),
+    RemoveUserFrame(String, String),
 }
 
+#[derive(Clone)]
 pub struct ChangeSet {
     pub actions: Vec<ModifyAction>,
+    pub from_filename: Option<FilenamePattern>,
     pub delete_all: bool,
-    pub format_file: bool,
+    pub convert: bool,
+    pub cover: CoverOptions,
+    /// Joins multiple values of a text frame when saving below ID3v2.4.
+    pub separator: String,
+    pub format_file: Option<Template>,
+    pub organize: Option<Organize>,
+    pub auto_number: bool,
+    pub id3v1: Option<Id3v1Mode>,
     pub print_details: bool,
+    pub list_user_frames: bool,
+    pub list_pictures: bool,
+    pub export_lyrics: bool,
+    pub export_lrc: bool,
+    /// Lets the exports replace files that already exist.
+    pub overwrite: bool,
+    pub extract_art: Option<ExtractArt>,
+    pub dry_run: bool,
+    pub output: OutputFormat,
+    pub include_pictures: bool,
+}
+
+impl ChangeSet {
+    /// Whether neither an edit nor a read-only operation was requested.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+            && self.from_filename.is_none()
+            && !(self.delete_all
+                || self.convert
+                || self.format_file.is_some()
+                || self.organize.is_some()
+                || self.auto_number
+                || self.id3v1.is_some_and(|m| m != Id3v1Mode::Write)
+                || self.print_details
+                || self.list_user_frames
+                || self.list_pictures
+                || self.export_lyrics
+                || self.export_lrc
+                || self.extract_art.is_some())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Id3v1Mode {
+    /// Writes the ID3v1 tag along with every tag that is saved.
+    Write,
+    /// Writes the ID3v1 tag wherever it differs from the ID3v2 tag.
+    Sync,
+    Strip,
+}
+
+pub enum Backup {
+    None,
+    Sibling,
+    Dir(PathBuf),
+}
+
+pub enum JournalMode {
+    Off,
+    Auto,
+    File(PathBuf),
+}
+
+#[derive(Clone, Copy)]
+pub enum VersionPolicy {
+    /// Saves with the version the tag was read with, ID3v2.4 for files without a tag.
+    Keep,
+    Force(Version),
+}
+
+impl VersionPolicy {
+    pub fn resolve(self, original: Option<Version>) -> Version {
+        match self {
+            VersionPolicy::Keep => original.unwrap_or(Version::Id3v24),
+            VersionPolicy::Force(version) => version,
+        }
+    }
 }
 
 pub struct AppConfig {
     pub target_path: PathBuf,
     pub recursive: bool,
+    pub import: Option<PathBuf>,
+    pub export_csv: Option<PathBuf>,
+    pub import_csv: Option<PathBuf>,
+    pub csv_fields: Vec<String>,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub include_hidden: bool,
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
     pub list_genres: bool,
-    pub version: Version,
+    pub version: VersionPolicy,
+    pub backup: Backup,
+    pub journal: JournalMode,
+    /// `Some(None)` undoes the most recent journal.
+    pub undo: Option<Option<PathBuf>>,
 }
 
 pub const ID3_GENRES: [&str; 192] = [
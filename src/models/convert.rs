@@ -0,0 +1,322 @@
+use id3::{
+    frame::{Content, Unknown},
+    Frame, Tag, TagLike, Version,
+};
+
+use crate::models::{dates, values};
+
+/// Frames introduced by ID3v2.4 without a counterpart in ID3v2.3.
+const V24_ONLY: [&str; 14] = [
+    "ASPI", "EQU2", "RVA2", "SEEK", "SIGN", "TDEN", "TDRL", "TDTG", "TMOO", "TPRO", "TSOA", "TSOP",
+    "TSOT", "TSST",
+];
+
+/// The ID3v2.4 involved people lists, holding pairs of roles and names.
+const PEOPLE_LISTS: [&str; 2] = ["TIPL", "TMCL"];
+
+/// Frames ID3v2.4 dropped without a counterpart. Dates and IPLS are translated instead.
+const V23_ONLY: [&str; 4] = ["EQUA", "RVAD", "TRDA", "TSIZ"];
+
+/// Rewrites the tag with the frames the version defines: dates are moved between TDRC/TDOR and
+/// TYER/TDAT/TIME/TORY, the involved people lists from TIPL/TMCL into IPLS and from IPLS into
+/// TIPL, and multiple text values are joined with `separator` below ID3v2.4, or rejoined with `/`
+/// when they were split on reading a lower version. Returns the frames that can't be
+/// represented, which are removed.
+pub fn convert(tag: &mut Tag, version: Version, separator: &str) -> Vec<Frame> {
+    dates::normalize(tag, version);
+
+    // ID3v2.4 keeps the musician credits of TMCL apart, ID3v2.3 only has the one list
+    let merged: &[&str] = if version == Version::Id3v24 {
+        &["TIPL"]
+    } else {
+        &PEOPLE_LISTS
+    };
+    let mut people = Vec::new();
+    for frame in merged.iter().flat_map(|id| tag.remove(id)) {
+        people.extend(
+            frame
+                .content()
+                .text_values()
+                .into_iter()
+                .flatten()
+                .map(String::from),
+        );
+    }
+    for frame in tag.remove("IPLS") {
+        if let Content::Unknown(u) = frame.content() {
+            people.extend(decode_strings(&u.data));
+        }
+    }
+
+    if version == Version::Id3v24 {
+        rejoin_values(tag);
+    }
+
+    let mut lost = Vec::new();
+    let dropped: &[&str] = if version == Version::Id3v24 {
+        &V23_ONLY
+    } else {
+        &V24_ONLY
+    };
+    for id in dropped {
+        lost.extend(tag.remove(id));
+    }
+
+    if !people.is_empty() {
+        if version == Version::Id3v24 {
+            tag.set_text_values("TIPL", people);
+        } else {
+            tag.add_frame(Frame::with_content(
+                "IPLS",
+                Content::Unknown(Unknown {
+                    data: encode_strings(&people),
+                    version,
+                }),
+            ));
+        }
+    }
+
+    if version != Version::Id3v24 {
+        join_values(tag, separator);
+    }
+
+    if version == Version::Id3v22 {
+        let unsupported: Vec<_> = tag
+            .frames()
+            .filter(|f| f.id_for_version(Version::Id3v22).is_none())
+            .map(|f| f.id().to_string())
+            .collect();
+        for id in unsupported {
+            lost.extend(tag.remove(&id));
+        }
+    }
+
+    lost
+}
+
+/// Joins the values of the multi-value text frames, which only ID3v2.4 can hold. Other frames are
+/// left to id3, which writes their nulls back as the `/` it read them from in ID3v2.3, so a track
+/// number like `3/12` keeps its total.
+pub fn join_values(tag: &mut Tag, separator: &str) {
+    let joined: Vec<_> = tag
+        .frames()
+        .filter(|f| values::SPLIT_FRAMES.contains(&f.id()))
+        .filter(|f| matches!(f.content(), Content::Text(t) if t.contains('\0')))
+        .map(|f| {
+            (
+                f.id().to_string(),
+                f.content().text().unwrap().replace('\0', separator),
+            )
+        })
+        .collect();
+    for (id, text) in joined {
+        tag.set_text(id, text);
+    }
+}
+
+/// Puts back the `/` id3 reads as a value separator in ID3v2.3 text frames, except in the
+/// multi-value frames and people lists. Saved as ID3v2.4, `3/12` would otherwise become two
+/// track numbers.
+pub fn rejoin_values(tag: &mut Tag) {
+    let joined: Vec<_> = tag
+        .frames()
+        .filter(|f| !values::SPLIT_FRAMES.contains(&f.id()) && !PEOPLE_LISTS.contains(&f.id()))
+        .filter(|f| matches!(f.content(), Content::Text(t) if t.contains('\0')))
+        .map(|f| {
+            (
+                f.id().to_string(),
+                f.content().text().unwrap().replace('\0', "/"),
+            )
+        })
+        .collect();
+    for (id, text) in joined {
+        tag.set_text(id, text);
+    }
+}
+
+/// The null separated strings of an IPLS frame, after its encoding byte.
+fn decode_strings(data: &[u8]) -> Vec<String> {
+    let Some((&encoding, rest)) = data.split_first() else {
+        return Vec::new();
+    };
+    let mut strings: Vec<String> = match encoding {
+        0 => rest
+            .split(|b| *b == 0)
+            .map(|s| s.iter().map(|&b| b as char).collect())
+            .collect(),
+        3 => rest
+            .split(|b| *b == 0)
+            .map(|s| String::from_utf8_lossy(s).into_owned())
+            .collect(),
+        _ => decode_utf16_strings(rest, encoding == 1),
+    };
+    while strings.last().is_some_and(|s| s.is_empty()) {
+        strings.pop();
+    }
+    strings
+}
+
+/// UTF-16 strings terminated by a null unit, each starting with a byte order mark when
+/// `with_bom` is set and big endian otherwise.
+fn decode_utf16_strings(data: &[u8], with_bom: bool) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut units = Vec::new();
+    let mut little_endian = false;
+    let mut at_start = true;
+
+    for pair in data.chunks_exact(2) {
+        if with_bom && at_start {
+            at_start = false;
+            match pair {
+                [0xFF, 0xFE] => {
+                    little_endian = true;
+                    continue;
+                }
+                [0xFE, 0xFF] => {
+                    little_endian = false;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        let unit = if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        };
+        if unit == 0 {
+            strings.push(String::from_utf16_lossy(&units));
+            units.clear();
+            at_start = true;
+        } else {
+            units.push(unit);
+        }
+    }
+    if !units.is_empty() {
+        strings.push(String::from_utf16_lossy(&units));
+    }
+    strings
+}
+
+/// UTF-16 with byte order marks, the one Unicode encoding every ID3v2 version supports.
+fn encode_strings(strings: &[String]) -> Vec<u8> {
+    let mut data = vec![1];
+    for s in strings {
+        data.extend([0xFF, 0xFE]);
+        data.extend(s.encode_utf16().flat_map(u16::to_le_bytes));
+        data.extend([0, 0]);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saves the tag as ID3v2.3 and reads it back, like editing a file twice.
+    fn round_trip(tag: &Tag) -> Tag {
+        let mut data = Vec::new();
+        tag.write_to(&mut data, Version::Id3v23).unwrap();
+        Tag::read_from2(std::io::Cursor::new(data)).unwrap()
+    }
+
+    #[test]
+    fn join_values_keeps_track_and_disc_totals() {
+        let mut tag = Tag::new();
+        tag.set_text("TRCK", "3/12");
+        tag.set_text("TPOS", "1/2");
+        let mut tag = round_trip(&tag);
+        join_values(&mut tag, "; ");
+
+        let tag = round_trip(&tag);
+        assert_eq!(tag.track(), Some(3));
+        assert_eq!(tag.total_tracks(), Some(12));
+        assert_eq!(tag.disc(), Some(1));
+        assert_eq!(tag.total_discs(), Some(2));
+    }
+
+    #[test]
+    fn convert_to_v24_rejoins_single_values() {
+        let mut tag = Tag::new();
+        tag.set_text("TRCK", "3/12");
+        tag.set_title("AC/DC");
+        tag.set_text_values("TPE1", ["X", "Y"]);
+        let mut tag = round_trip(&tag);
+        convert(&mut tag, Version::Id3v24, "/");
+
+        assert_eq!(
+            tag.get("TRCK").and_then(|f| f.content().text()),
+            Some("3/12")
+        );
+        assert_eq!(tag.title(), Some("AC/DC"));
+        assert_eq!(tag.artists(), Some(vec!["X", "Y"]));
+    }
+
+    #[test]
+    fn convert_to_v23_splits_dates_and_drops_v24_frames() {
+        let mut tag = Tag::new();
+        tag.set_date_recorded("1982-05-21T14:30".parse().unwrap());
+        tag.set_text("TMOO", "Calm");
+        let lost = convert(&mut tag, Version::Id3v23, "/");
+
+        assert_eq!(lost.iter().map(Frame::id).collect::<Vec<_>>(), ["TMOO"]);
+        assert!(tag.get("TDRC").is_none());
+        assert_eq!(tag.year(), Some(1982));
+        assert_eq!(
+            tag.get("TDAT").and_then(|f| f.content().text()),
+            Some("2105")
+        );
+        assert_eq!(
+            tag.get("TIME").and_then(|f| f.content().text()),
+            Some("1430")
+        );
+    }
+
+    #[test]
+    fn involved_people_survive_a_round_trip() {
+        let people = ["producer", "Tatsuro Yamashita", "mixer", "Someone"];
+        let mut tag = Tag::new();
+        tag.set_text_values("TIPL", people);
+        convert(&mut tag, Version::Id3v23, "/");
+        assert!(tag.get("TIPL").is_none());
+
+        let mut tag = round_trip(&tag);
+        convert(&mut tag, Version::Id3v24, "/");
+        let values: Vec<_> = tag
+            .get("TIPL")
+            .and_then(|f| f.content().text_values())
+            .unwrap()
+            .collect();
+        assert_eq!(values, people);
+    }
+
+    #[test]
+    fn convert_to_v24_keeps_musician_credits_apart() {
+        let mut tag = Tag::new();
+        tag.set_text_values("TIPL", ["producer", "Someone"]);
+        tag.set_text_values("TMCL", ["piano", "Someone Else"]);
+        convert(&mut tag, Version::Id3v24, "/");
+
+        let values = |id| -> Vec<&str> {
+            tag.get(id)
+                .unwrap()
+                .content()
+                .text_values()
+                .unwrap()
+                .collect()
+        };
+        assert_eq!(values("TIPL"), ["producer", "Someone"]);
+        assert_eq!(values("TMCL"), ["piano", "Someone Else"]);
+    }
+
+    #[test]
+    fn join_values_uses_the_separator_for_multiple_values() {
+        let mut tag = Tag::new();
+        tag.set_text_values("TPE1", ["X", "Y"]);
+        tag.set_title("AC\0DC");
+        join_values(&mut tag, " & ");
+
+        assert_eq!(tag.artist(), Some("X & Y"));
+        assert_eq!(tag.title(), Some("AC\0DC"));
+    }
+}
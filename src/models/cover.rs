@@ -0,0 +1,200 @@
+use std::{fs, io::Cursor, path::Path};
+
+use id3::frame::PictureType;
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageFormat, ImageReader,
+};
+
+/// Lowest JPEG quality tried to fit an image into the size budget before scaling it down.
+const MIN_BUDGET_QUALITY: u8 = 40;
+/// Images aren't scaled below this to fit the size budget.
+const MIN_BUDGET_SIZE: u32 = 100;
+
+/// Every picture type ID3 defines, in the order of their codes.
+const PICTURE_TYPES: [PictureType; 21] = [
+    PictureType::Other,
+    PictureType::Icon,
+    PictureType::OtherIcon,
+    PictureType::CoverFront,
+    PictureType::CoverBack,
+    PictureType::Leaflet,
+    PictureType::Media,
+    PictureType::LeadArtist,
+    PictureType::Artist,
+    PictureType::Conductor,
+    PictureType::Band,
+    PictureType::Composer,
+    PictureType::Lyricist,
+    PictureType::RecordingLocation,
+    PictureType::DuringRecording,
+    PictureType::DuringPerformance,
+    PictureType::ScreenCapture,
+    PictureType::BrightFish,
+    PictureType::Illustration,
+    PictureType::BandLogo,
+    PictureType::PublisherLogo,
+];
+
+/// Pictures to remove, by type or by their number in `--list-pictures`.
+#[derive(Clone)]
+pub enum PictureSelector {
+    Type(PictureType),
+    Index(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CoverFormat {
+    Jpeg,
+    Png,
+    /// Embeds JPEG and PNG images untouched unless they have to be scaled or exceed the size
+    /// budget, keeping their format. Anything else, like WebP, becomes a PNG, and PNGs over
+    /// the budget fall back to JPEG.
+    Auto,
+}
+
+#[derive(Clone)]
+pub struct CoverOptions {
+    /// Embeds the file's bytes as they are, ignoring every other option.
+    pub raw: bool,
+    pub format: CoverFormat,
+    pub quality: u8,
+    /// Longest side in pixels, larger images are scaled down.
+    pub max_size: Option<u32>,
+    /// Largest embedded image in bytes, reached by lowering the JPEG quality, then the size.
+    pub budget: Option<usize>,
+}
+
+/// Parses a picture type named like `--list-pictures` shows it, ignoring case and accepting
+/// dashes or underscores for spaces, so `band-logo` is `Band logo`. `front` and `back` are
+/// short for the covers.
+pub fn picture_type(name: &str) -> Result<PictureType, String> {
+    let name = name.trim().to_lowercase().replace(['-', '_'], " ");
+    match name.as_str() {
+        "front" => return Ok(PictureType::CoverFront),
+        "back" => return Ok(PictureType::CoverBack),
+        _ => {}
+    }
+    PICTURE_TYPES
+        .into_iter()
+        .find(|t| t.to_string().to_lowercase() == name)
+        .ok_or_else(|| {
+            format!(
+                "Unknown picture type {:?}, expected one of: {}",
+                name,
+                PICTURE_TYPES
+                    .into_iter()
+                    .map(type_name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+/// The picture type as written on the command line, like `back-cover`.
+pub fn type_name(picture_type: PictureType) -> String {
+    picture_type.to_string().to_lowercase().replace(' ', "-")
+}
+
+/// A number from `--list-pictures` or a picture type.
+pub fn picture_selector(s: &str) -> Result<PictureSelector, String> {
+    match s.trim().parse::<usize>() {
+        Ok(0) => Err("Pictures are numbered from 1".to_string()),
+        Ok(n) => Ok(PictureSelector::Index(n)),
+        Err(_) => picture_type(s).map(PictureSelector::Type),
+    }
+}
+
+/// Width and height of an embedded image, if its format is known.
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// The MIME type and bytes of the image to embed.
+pub fn prepare(path: &Path, options: &CoverOptions) -> Result<(String, Vec<u8>), String> {
+    let reader = ImageReader::open(path)
+        .map_err(|e| format!("Error opening image :: {}", e))?
+        .with_guessed_format()
+        .map_err(|e| format!("Error guessing format :: {}", e))?;
+    let source = reader.format();
+
+    if options.raw {
+        let format = source.ok_or("Error guessing format :: unknown image format")?;
+        let data = fs::read(path).map_err(|e| format!("Error reading image :: {}", e))?;
+        return Ok((format.to_mime_type().to_string(), data));
+    }
+
+    let mut img = reader
+        .decode()
+        .map_err(|e| format!("Error decoding image :: {}", e))?;
+    let mut scaled = false;
+    if let Some(max) = options
+        .max_size
+        .filter(|max| img.width() > *max || img.height() > *max)
+    {
+        img = img.resize(max, max, FilterType::Lanczos3);
+        scaled = true;
+    }
+
+    if let (CoverFormat::Auto, false, Some(format @ (ImageFormat::Jpeg | ImageFormat::Png))) =
+        (options.format, scaled, source)
+    {
+        let data = fs::read(path).map_err(|e| format!("Error reading image :: {}", e))?;
+        if options.budget.is_none_or(|b| data.len() <= b) {
+            return Ok((format.to_mime_type().to_string(), data));
+        }
+    }
+
+    let png = match options.format {
+        CoverFormat::Jpeg => false,
+        CoverFormat::Png => true,
+        CoverFormat::Auto => source != Some(ImageFormat::Jpeg),
+    };
+    if png {
+        let data = encode_png(&img)?;
+        let fits = options.budget.is_none_or(|b| data.len() <= b);
+        if fits || options.format == CoverFormat::Png {
+            return Ok(("image/png".to_string(), data));
+        }
+    }
+
+    let mut quality = options.quality;
+    loop {
+        let data = encode_jpeg(&img, quality)?;
+        let Some(budget) = options.budget.filter(|b| data.len() > *b) else {
+            return Ok(("image/jpeg".to_string(), data));
+        };
+        if quality > MIN_BUDGET_QUALITY {
+            quality = quality.saturating_sub(10).max(MIN_BUDGET_QUALITY);
+        } else if img.width().max(img.height()) * 3 / 4 >= MIN_BUDGET_SIZE {
+            img = img.resize(
+                img.width() * 3 / 4,
+                img.height() * 3 / 4,
+                FilterType::Lanczos3,
+            );
+        } else {
+            return Err(format!(
+                "Error processing image :: can't fit it into {} bytes",
+                budget
+            ));
+        }
+    }
+}
+
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    DynamicImage::ImageRgb8(img.to_rgb8())
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut data, quality))
+        .map_err(|e| format!("Error processing image :: {}", e))?;
+    Ok(data)
+}
+
+fn encode_png(img: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut data = Cursor::new(Vec::new());
+    img.write_to(&mut data, ImageFormat::Png)
+        .map_err(|e| format!("Error processing image :: {}", e))?;
+    Ok(data.into_inner())
+}
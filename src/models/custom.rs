@@ -0,0 +1,168 @@
+use id3::{
+    frame::{
+        Comment, Content, ExtendedLink, ExtendedText, Lyrics, Popularimeter, UniqueFileIdentifier,
+    },
+    Frame,
+};
+
+const FIELD_SEPARATOR: char = '|';
+
+/// Text frames defined by ID3v2.3 and ID3v2.4, plus the iTunes ones most players read.
+const TEXT_FRAMES: [&str; 58] = [
+    "TALB", "TBPM", "TCOM", "TCON", "TCOP", "TDAT", "TDEN", "TDLY", "TDOR", "TDRC", "TDRL", "TDTG",
+    "TENC", "TEXT", "TFLT", "TIME", "TIPL", "TIT1", "TIT2", "TIT3", "TKEY", "TLAN", "TLEN", "TMCL",
+    "TMED", "TMOO", "TOAL", "TOFN", "TOLY", "TOPE", "TORY", "TOWN", "TPE1", "TPE2", "TPE3", "TPE4",
+    "TPOS", "TPRO", "TPUB", "TRCK", "TRDA", "TRSN", "TRSO", "TSIZ", "TSOA", "TSOP", "TSOT", "TSRC",
+    "TSSE", "TSST", "TYER", "TCMP", "TSO2", "TSOC", "TCAT", "TDES", "TGID", "TKWD",
+];
+
+/// Link frames defined by ID3v2.3 and ID3v2.4, plus the iTunes podcast feed.
+const LINK_FRAMES: [&str; 9] = [
+    "WCOM", "WCOP", "WOAF", "WOAR", "WOAS", "WORS", "WPAY", "WPUB", "WFED",
+];
+
+/// Builds the frame for `-C <id> <value>` with the content type the ID requires.
+///
+/// Structured frames take their fields separated by `|`, for example `eng|description|text`
+/// for COMM. IDs that can't be written from a string are rejected here, before any tag is
+/// touched, so an invalid frame can't make the save fail half way.
+pub fn build_frame(id: &str, value: &str) -> Result<Frame, String> {
+    if id.len() != 4
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    {
+        return Err(format!(
+            "Invalid frame ID {:?}, expected four uppercase letters or digits",
+            id
+        ));
+    }
+
+    let content = match id {
+        "TXXX" => {
+            let (description, value) = split_optional(value);
+            Content::ExtendedText(ExtendedText {
+                description: description.to_string(),
+                value: value.to_string(),
+            })
+        }
+        "WXXX" => {
+            let (description, link) = split_optional(value);
+            Content::ExtendedLink(ExtendedLink {
+                description: description.to_string(),
+                link: link.to_string(),
+            })
+        }
+        "COMM" | "USLT" => {
+            let (lang, description, text) = split_lang_description(id, value)?;
+            if id == "COMM" {
+                Content::Comment(Comment {
+                    lang,
+                    description,
+                    text,
+                })
+            } else {
+                Content::Lyrics(Lyrics {
+                    lang,
+                    description,
+                    text,
+                })
+            }
+        }
+        "POPM" => {
+            let mut fields = value.splitn(3, FIELD_SEPARATOR);
+            let user = fields.next().unwrap_or_default();
+            let rating = fields
+                .next()
+                .ok_or("POPM expects 'user|rating[|counter]'")?
+                .trim()
+                .parse::<u8>()
+                .map_err(|_| "POPM rating must be a number between 0 and 255")?;
+            let counter = match fields.next() {
+                Some(c) => c
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| "POPM counter must be a positive number")?,
+                None => 0,
+            };
+            Content::Popularimeter(Popularimeter {
+                user: user.to_string(),
+                rating,
+                counter,
+            })
+        }
+        "UFID" => {
+            let (owner, identifier) = value
+                .split_once(FIELD_SEPARATOR)
+                .ok_or("UFID expects 'owner|identifier'")?;
+            if owner.is_empty() {
+                return Err("UFID owner identifier can not be empty".to_string());
+            }
+            if identifier.len() > 64 {
+                return Err("UFID identifier can be at most 64 bytes".to_string());
+            }
+            Content::UniqueFileIdentifier(UniqueFileIdentifier {
+                owner_identifier: owner.to_string(),
+                identifier: identifier.as_bytes().to_vec(),
+            })
+        }
+        "APIC" => return Err("Use --cover-art to set APIC frames".to_string()),
+        "SYLT" => return Err("Use --lrc to set SYLT frames".to_string()),
+        _ if TEXT_FRAMES.contains(&id) => Content::Text(value.to_string()),
+        _ if LINK_FRAMES.contains(&id) => Content::Link(value.to_string()),
+        _ if id.starts_with('T') || id.starts_with('W') => {
+            return Err(format!(
+                "Frame ID {} is not defined by ID3v2, use TXXX or WXXX with a description for frames of your own",
+                id
+            ))
+        }
+        _ => {
+            return Err(format!(
+                "Frame ID {} can not be set with --custom, only text (T***), link (W***), TXXX, WXXX, COMM, USLT, POPM and UFID frames are supported",
+                id
+            ))
+        }
+    };
+
+    Ok(Frame::with_content(id, content))
+}
+
+/// Splits `description|value`, treating a value without a separator as having no description.
+fn split_optional(value: &str) -> (&str, &str) {
+    value.split_once(FIELD_SEPARATOR).unwrap_or(("", value))
+}
+
+/// Splits `[[lang|]description|]text`, defaulting to English and an empty description.
+fn split_lang_description(id: &str, value: &str) -> Result<(String, String, String), String> {
+    let fields: Vec<&str> = value.splitn(3, FIELD_SEPARATOR).collect();
+    let (lang, description, text) = match fields[..] {
+        [text] => ("eng", "", text),
+        [description, text] => ("eng", description, text),
+        [lang, description, text] => (lang, description, text),
+        _ => unreachable!(),
+    };
+    if lang.len() != 3 || !lang.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!(
+            "{} language must be a three letter ISO-639-2 code, e.g. 'eng'",
+            id
+        ));
+    }
+    Ok((
+        lang.to_ascii_lowercase(),
+        description.to_string(),
+        text.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_frame_rejects_undefined_ids() {
+        assert!(build_frame("TIT3", "Frogs").is_ok());
+        assert!(build_frame("WOAR", "https://example.com").is_ok());
+        assert!(build_frame("TZZZ", "Frogs").is_err());
+        assert!(build_frame("WZZZ", "https://example.com").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use id3::{Tag, TagLike, Timestamp, Version};
+
+/// Frames making up the recording date in ID3v2.3, replaced by TDRC in ID3v2.4.
+pub const RECORDED_V23: [&str; 3] = ["TYER", "TDAT", "TIME"];
+
+/// Parses `yyyy[-MM[-dd[THH[:mm[:ss]]]]]`. The id3 parser skips over text it doesn't
+/// understand, so only input that formats back unchanged is accepted.
+pub fn parse(s: &str) -> Result<Timestamp, String> {
+    s.trim()
+        .parse::<Timestamp>()
+        .ok()
+        .filter(|ts| ts.to_string() == s.trim())
+        .ok_or_else(|| {
+            format!(
+                "Invalid date {:?}, expected a year or an ISO 8601 date such as 1982-05-21T14:30",
+                s
+            )
+        })
+}
+
+/// The recording date from TDRC, or from TYER, TDAT (DDMM) and TIME (HHMM) in ID3v2.3 tags.
+pub fn recorded(tag: &Tag) -> Option<Timestamp> {
+    if let Some(ts) = tag.date_recorded() {
+        return Some(ts);
+    }
+    let mut ts = Timestamp {
+        year: tag.year()?,
+        ..Default::default()
+    };
+    if let Some((day, month)) = text(tag, "TDAT").and_then(split_pair) {
+        ts.month = Some(month);
+        ts.day = Some(day);
+        if let Some((hour, minute)) = text(tag, "TIME").and_then(split_pair) {
+            ts.hour = Some(hour);
+            ts.minute = Some(minute);
+        }
+    }
+    Some(ts)
+}
+
+/// The original release date from TDOR, or the year from TORY.
+pub fn original(tag: &Tag) -> Option<Timestamp> {
+    tag.original_date_released().or_else(|| {
+        Some(Timestamp {
+            year: text(tag, "TORY")?.trim().parse().ok()?,
+            ..Default::default()
+        })
+    })
+}
+
+pub fn set_recorded(tag: &mut Tag, ts: Timestamp) {
+    tag.remove("TDRC");
+    for id in RECORDED_V23 {
+        tag.remove(id);
+    }
+    tag.set_date_recorded(ts);
+}
+
+pub fn set_original(tag: &mut Tag, ts: Timestamp) {
+    tag.remove("TORY");
+    tag.set_original_date_released(ts);
+}
+
+/// Moves the dates into the frames the version defines: TDRC and TDOR for ID3v2.4, TYER, TDAT,
+/// TIME and TORY otherwise. ID3v2.3 has no release date, so a TDRL the file already had is kept as
+/// it is, while `--release-date` refuses to add one and `--convert` removes it.
+pub fn normalize(tag: &mut Tag, version: Version) {
+    let recorded = recorded(tag);
+    let original = original(tag);
+
+    if version == Version::Id3v24 {
+        for id in RECORDED_V23 {
+            tag.remove(id);
+        }
+        tag.remove("TORY");
+        if let Some(ts) = recorded {
+            tag.set_date_recorded(ts);
+        }
+        if let Some(ts) = original {
+            tag.set_original_date_released(ts);
+        }
+        return;
+    }
+
+    tag.remove("TDRC");
+    tag.remove("TDOR");
+    for id in RECORDED_V23 {
+        tag.remove(id);
+    }
+    if let Some(ts) = recorded {
+        tag.set_year(ts.year);
+        if let (Some(month), Some(day)) = (ts.month, ts.day) {
+            tag.set_text("TDAT", format!("{:02}{:02}", day, month));
+            if let (Some(hour), Some(minute)) = (ts.hour, ts.minute) {
+                tag.set_text("TIME", format!("{:02}{:02}", hour, minute));
+            }
+        }
+    }
+    if let Some(ts) = original {
+        tag.set_text("TORY", format!("{:04}", ts.year));
+    }
+}
+
+fn text<'a>(tag: &'a Tag, id: &str) -> Option<&'a str> {
+    tag.get(id)?.content().text()
+}
+
+/// `DDMM` or `HHMM` into its two numbers.
+fn split_pair(s: &str) -> Option<(u8, u8)> {
+    let s = s.trim();
+    if s.len() != 4 || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((s[..2].parse().ok()?, s[2..].parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accepts_years_and_iso_dates() {
+        assert_eq!(parse("1982").map(|t| t.year), Ok(1982));
+        let ts = parse(" 1982-05-21T14:30 ").unwrap();
+        assert_eq!(
+            (ts.month, ts.day, ts.hour, ts.minute),
+            (Some(5), Some(21), Some(14), Some(30))
+        );
+    }
+
+    #[test]
+    fn parse_refuses_what_it_would_drop() {
+        assert!(parse("21/05/1982").is_err());
+        assert!(parse("1982-05-21 14:30").is_err());
+        assert!(parse("May 1982").is_err());
+    }
+
+    #[test]
+    fn recorded_reads_v23_frames() {
+        let mut tag = Tag::new();
+        tag.set_year(1982);
+        tag.set_text("TDAT", "2105");
+        tag.set_text("TIME", "1430");
+        assert_eq!(recorded(&tag), parse("1982-05-21T14:30").ok());
+    }
+
+    #[test]
+    fn normalize_round_trips_between_versions() {
+        let ts = parse("1982-05-21T14:30").unwrap();
+        let mut tag = Tag::new();
+        set_recorded(&mut tag, ts);
+        set_original(&mut tag, parse("1981").unwrap());
+
+        normalize(&mut tag, Version::Id3v23);
+        assert!(tag.get("TDRC").is_none());
+        assert_eq!(text(&tag, "TORY"), Some("1981"));
+
+        normalize(&mut tag, Version::Id3v24);
+        assert_eq!(tag.date_recorded(), Some(ts));
+        assert_eq!(tag.original_date_released().map(|t| t.year), Some(1981));
+        assert!(RECORDED_V23.iter().all(|id| tag.get(id).is_none()));
+    }
+}
@@ -0,0 +1,155 @@
+use std::{io::Cursor, path::Path};
+
+use id3::{frame::Content, Frame, TagLike};
+
+use crate::{
+    models::{
+        id3v1::Trailer,
+        track::{display_content, frame_description},
+    },
+    status,
+};
+
+pub enum FrameChange<'a> {
+    Added(&'a Frame),
+    Changed(&'a Frame, &'a Frame),
+    Removed(&'a Frame),
+}
+
+/// What tells apart frames sharing an ID, mirroring the uniqueness rules ID3 applies on write.
+fn frame_key(frame: &Frame) -> (&str, String) {
+    let discriminator = match frame.content() {
+        Content::Comment(c) => format!("{}\0{}", c.lang, c.description),
+        Content::Lyrics(l) => format!("{}\0{}", l.lang, l.description),
+        Content::SynchronisedLyrics(l) => format!("{}\0{}", l.lang, l.description),
+        Content::Picture(p) => p.picture_type.to_string(),
+        Content::Link(l) if frame.id() == "WCOM" || frame.id() == "WOAR" => l.clone(),
+        content => frame_description(content).to_string(),
+    };
+    (frame.id(), discriminator)
+}
+
+/// The tag as saving it in `version` would store it. Below ID3v2.4 id3 writes the null between
+/// values as `/`, so a tag read from an ID3v2.3 file compares equal to the joined one saved back.
+pub fn as_written(tag: &id3::Tag, version: Option<id3::Version>) -> id3::Tag {
+    let mut tag = tag.clone();
+    if version.is_some_and(|v| v != id3::Version::Id3v24) {
+        let joined: Vec<_> = tag
+            .frames()
+            .filter_map(|f| match f.content() {
+                Content::Text(t) if t.contains('\0') => {
+                    Some((f.id().to_string(), t.replace('\0', "/")))
+                }
+                _ => None,
+            })
+            .collect();
+        for (id, text) in joined {
+            tag.set_text(id, text);
+        }
+    }
+    tag
+}
+
+/// Frame-level differences between two tags, sorted by frame ID.
+pub fn diff_tags<'a>(before: &'a id3::Tag, after: &'a id3::Tag) -> Vec<FrameChange<'a>> {
+    let mut remaining: Vec<&Frame> = after.frames().collect();
+    let mut changes = Vec::new();
+
+    for old in before.frames() {
+        let key = frame_key(old);
+        match remaining.iter().position(|f| frame_key(f) == key) {
+            Some(i) => {
+                let new = remaining.remove(i);
+                if old.content() != new.content() {
+                    changes.push(FrameChange::Changed(old, new));
+                }
+            }
+            None => changes.push(FrameChange::Removed(old)),
+        }
+    }
+    changes.extend(remaining.into_iter().map(FrameChange::Added));
+
+    changes.sort_by(|a, b| change_id(a).cmp(change_id(b)));
+    changes
+}
+
+fn change_id<'a>(change: &FrameChange<'a>) -> &'a str {
+    match change {
+        FrameChange::Added(f) | FrameChange::Changed(f, _) | FrameChange::Removed(f) => f.id(),
+    }
+}
+
+fn label(frame: &Frame) -> String {
+    match frame_description(frame.content()) {
+        "" => frame.id().to_string(),
+        description => format!("{} {:?}", frame.id(), description),
+    }
+}
+
+/// The fields of a raw ID3v1 tag that are set.
+fn id3v1_summary(data: &[u8]) -> String {
+    let Ok(tag) = id3::v1::Tag::read_from(Cursor::new(data)) else {
+        return format!("{} bytes", data.len());
+    };
+    let track = tag.track.map(|n| n.to_string()).unwrap_or_default();
+    let fields: Vec<_> = [
+        ("title", tag.title.as_str()),
+        ("artist", &tag.artist),
+        ("album", &tag.album),
+        ("year", &tag.year),
+        ("track", &track),
+        ("genre", tag.genre().unwrap_or_default()),
+    ]
+    .into_iter()
+    .filter(|(_, v)| !v.trim().is_empty())
+    .map(|(name, v)| format!("{}={:?}", name, v.trim()))
+    .collect();
+    fields.join(", ")
+}
+
+/// Prints what saving the changed tag, the ID3v1 tag and renaming the file would do.
+pub fn print_diff(
+    path: &Path,
+    before: &id3::Tag,
+    after: &id3::Tag,
+    id3v1: Option<&[u8]>,
+    trailer: &Trailer,
+    rename: Option<&Path>,
+) {
+    status!("Dry run: {:?}", path);
+
+    let changes = diff_tags(before, after);
+    if changes.is_empty() && *trailer == Trailer::Keep && rename.is_none() {
+        status!("  No changes");
+    }
+    for change in changes {
+        match change {
+            FrameChange::Added(f) => {
+                status!("  + {}: {}", label(f), display_content(f.content()))
+            }
+            FrameChange::Changed(old, new) => status!(
+                "  ~ {}: {} -> {}",
+                label(new),
+                display_content(old.content()),
+                display_content(new.content())
+            ),
+            FrameChange::Removed(f) => {
+                status!("  - {}: {}", label(f), display_content(f.content()))
+            }
+        }
+    }
+    match (trailer, id3v1) {
+        (Trailer::Keep, _) => {}
+        (Trailer::Strip, Some(old)) => status!("  - ID3v1: {}", id3v1_summary(old)),
+        (Trailer::Strip, None) => {}
+        (Trailer::Write(new), Some(old)) => status!(
+            "  ~ ID3v1: {} -> {}",
+            id3v1_summary(old),
+            id3v1_summary(new)
+        ),
+        (Trailer::Write(new), None) => status!("  + ID3v1: {}", id3v1_summary(new)),
+    }
+    if let Some(new_path) = rename {
+        status!("  Rename -> {:?}", new_path);
+    }
+}
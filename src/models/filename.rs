@@ -0,0 +1,181 @@
+use std::path::Path;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::models::{controls::ModifyAction, manifest, table};
+
+/// A pattern such as `%artist%/%album%/%track% - %title%.mp3` matched against the end of a
+/// file's path. A placeholder takes an optional regex, `%track:\d{2}%`, and `%_%` matches
+/// without setting anything.
+#[derive(Clone, Debug)]
+pub struct FilenamePattern {
+    source: String,
+    regex: Regex,
+    fields: Vec<String>,
+    components: usize,
+}
+
+impl FilenamePattern {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut expr = String::from("^");
+        let mut fields = Vec::new();
+        let mut components = 1;
+
+        let mut rest = source;
+        while let Some(start) = rest.find('%') {
+            let literal = &rest[..start];
+            components += literal.matches('/').count();
+            expr.push_str(&regex::escape(literal));
+
+            let after = &rest[start + 1..];
+            let end = after
+                .find('%')
+                .ok_or_else(|| format!("Unclosed placeholder in {:?}", source))?;
+            let placeholder = &after[..end];
+            rest = &after[end + 1..];
+
+            if placeholder.is_empty() {
+                expr.push('%');
+                continue;
+            }
+            let (field, custom) = split_placeholder(placeholder);
+            if field == "path" || (field != "_" && table::check_field(field).is_err()) {
+                return Err(format!("Unknown placeholder %{}% in {:?}", field, source));
+            }
+            components += custom.map_or(0, separators);
+            let group = custom.unwrap_or(match field {
+                "track" => r"\d+",
+                "year" => r"\d{4}",
+                _ => "[^/]+?",
+            });
+            expr.push_str(&format!("(?P<f{}>{})", fields.len(), group));
+            fields.push(field.to_string());
+        }
+        components += rest.matches('/').count();
+        // The extension is matched ignoring case, so `%title%.mp3` also reads `Track.MP3`
+        match rest
+            .len()
+            .checked_sub(4)
+            .filter(|&i| rest.is_char_boundary(i))
+        {
+            Some(i) if rest[i..].eq_ignore_ascii_case(".mp3") => {
+                expr.push_str(&regex::escape(&rest[..i]));
+                expr.push_str(r"(?i:\.mp3)");
+            }
+            _ => expr.push_str(&regex::escape(rest)),
+        }
+        expr.push('$');
+
+        let regex =
+            Regex::new(&expr).map_err(|e| format!("Invalid pattern {:?} :: {}", source, e))?;
+        Ok(FilenamePattern {
+            source: source.to_string(),
+            regex,
+            fields,
+            components,
+        })
+    }
+
+    /// Actions setting the fields captured from the last components of the path. Empty
+    /// captures are skipped.
+    pub fn actions(&self, path: &Path) -> Result<Vec<ModifyAction>, String> {
+        let mut parts: Vec<_> = path
+            .components()
+            .rev()
+            .take(self.components)
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        parts.reverse();
+        let subject = parts.join("/");
+
+        let captures = self.regex.captures(&subject).ok_or_else(|| {
+            format!(
+                "Error parsing file name :: {:?} doesn't match {:?}",
+                subject, self.source
+            )
+        })?;
+
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let mut actions = Vec::new();
+        for (i, field) in self.fields.iter().enumerate() {
+            let Some(value) = captures.name(&format!("f{}", i)) else {
+                continue;
+            };
+            let value = value.as_str().trim();
+            if field == "_" || value.is_empty() {
+                continue;
+            }
+            actions.extend(
+                manifest::field_actions(field, &Value::String(value.to_string()), base_dir)
+                    .map_err(|e| format!("Error parsing file name {:?} :: {}", subject, e))?,
+            );
+        }
+        Ok(actions)
+    }
+}
+
+/// `field[:regex]`, where user frame fields carry their own `txxx:<desc>` prefix.
+fn split_placeholder(placeholder: &str) -> (&str, Option<&str>) {
+    let skip = if placeholder.starts_with("txxx:") || placeholder.starts_with("wxxx:") {
+        "txxx:".len()
+    } else {
+        0
+    };
+    match placeholder[skip..].find(':') {
+        Some(i) => (&placeholder[..skip + i], Some(&placeholder[skip + i + 1..])),
+        None => (placeholder, None),
+    }
+}
+
+/// The path separators a placeholder's regex matches, leaving out those in character classes
+/// such as `[^/]`.
+fn separators(regex: &str) -> usize {
+    let mut count = 0;
+    let mut in_class = false;
+    let mut chars = regex.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // An escaped character is never special, but `\/` still matches a separator
+            '\\' => count += usize::from(chars.next() == Some('/') && !in_class),
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(pattern: &str, path: &str) -> Vec<String> {
+        let actions = FilenamePattern::parse(pattern)
+            .unwrap()
+            .actions(Path::new(path))
+            .unwrap();
+        actions
+            .into_iter()
+            .filter_map(|a| match a {
+                ModifyAction::Title(t) => Some(t),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extension_ignores_case() {
+        assert_eq!(titles("%title%.mp3", "music/Track.MP3"), ["Track"]);
+    }
+
+    #[test]
+    fn separators_in_a_placeholder_regex_are_counted() {
+        assert_eq!(
+            titles(r"%title:[^/]+/[^/]+%.mp3", "music/Love Trip/Intro.mp3"),
+            ["Love Trip/Intro"]
+        );
+        assert_eq!(separators(r"a\/b[/]c/d"), 2);
+    }
+}
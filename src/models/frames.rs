@@ -0,0 +1,162 @@
+use std::{
+    fs::File,
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use id3::{Frame, Tag, TagLike};
+
+/// Frames id3 keeps one of per picture type, or per language and content type, when it reads a
+/// tag, while a file may hold several: booklet pages sharing the `Other` type, or lyrics told
+/// apart by their description. Listed with their ID3v2.2 IDs.
+const REPEATED: [(&str, &str); 2] = [("APIC", "PIC"), ("SYLT", "SLT")];
+const HEADER_LEN: usize = 10;
+
+/// Reads the tag at the start of the file with every repeated frame it holds.
+pub fn read_from_path(path: &Path) -> id3::Result<Tag> {
+    let mut tag = Tag::read_from_path(path)?;
+    if let Some(data) = raw_tag(path) {
+        restore_repeated(&mut tag, &data);
+    }
+    Ok(tag)
+}
+
+/// Puts back the repeated frames id3 dropped reading `data`, the raw tag starting with its
+/// header. Tags whose frames can't be walked safely, like unsynchronised ones, are left as read.
+pub fn restore_repeated(tag: &mut Tag, data: &[u8]) {
+    let Some(frames) = repeated_frames(data) else {
+        return;
+    };
+    for (id, _) in REPEATED {
+        let found: Vec<Frame> = frames.iter().filter(|f| f.id() == id).cloned().collect();
+        if found.len() > tag.frames().filter(|f| f.id() == id).count() {
+            tag.remove(id);
+            tag.extend(found);
+        }
+    }
+}
+
+fn raw_tag(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let mut data = vec![0; HEADER_LEN];
+    file.read_exact(&mut data).ok()?;
+    if !data.starts_with(b"ID3") {
+        return None;
+    }
+    let size = syncsafe(&data[6..10]);
+    file.take(size as u64).read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+/// Decodes the repeated frames one by one, each wrapped in a tag of its own so id3 can't merge
+/// them.
+fn repeated_frames(data: &[u8]) -> Option<Vec<Frame>> {
+    let header = data.get(..HEADER_LEN)?;
+    let (major, flags) = (header[3], header[5]);
+    if !header.starts_with(b"ID3") || flags & 0x80 != 0 || !(2..=4).contains(&major) {
+        return None;
+    }
+    let end = (HEADER_LEN + syncsafe(&header[6..10])).min(data.len());
+    let mut pos = HEADER_LEN;
+    if flags & 0x40 != 0 {
+        let size = data.get(pos..pos + 4)?;
+        pos += match major {
+            3 => 4 + u32::from_be_bytes(size.try_into().ok()?) as usize,
+            4 => syncsafe(size),
+            _ => return None,
+        };
+    }
+
+    let (id_len, frame_header_len) = if major == 2 { (3, 6) } else { (4, 10) };
+    let mut frames = Vec::new();
+    while pos + frame_header_len <= end {
+        let id = &data[pos..pos + id_len];
+        if !id
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        {
+            break;
+        }
+        let size = &data[pos + id_len..];
+        let len = match major {
+            2 => u32::from_be_bytes([0, size[0], size[1], size[2]]) as usize,
+            3 => u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize,
+            _ => syncsafe(&size[..4]),
+        };
+        let frame_end = pos + frame_header_len + len;
+        if frame_end > end {
+            return None;
+        }
+
+        let wanted = REPEATED
+            .iter()
+            .any(|(v3, v2)| id == v3.as_bytes() || id == v2.as_bytes());
+        if wanted {
+            let mut single = header.to_vec();
+            single[5] = 0;
+            single[6..10].copy_from_slice(&to_syncsafe(frame_end - pos));
+            single.extend(&data[pos..frame_end]);
+            let tag = Tag::read_from2(Cursor::new(single)).ok()?;
+            frames.extend(tag.frames().cloned());
+        }
+        pos = frame_end;
+    }
+    Some(frames)
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |n, b| (n << 7) | (*b & 0x7F) as usize)
+}
+
+fn to_syncsafe(n: usize) -> [u8; 4] {
+    [
+        (n >> 21) as u8 & 0x7F,
+        (n >> 14) as u8 & 0x7F,
+        (n >> 7) as u8 & 0x7F,
+        n as u8 & 0x7F,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use id3::{
+        frame::{Picture, PictureType},
+        Version,
+    };
+
+    use super::*;
+
+    fn pages() -> Tag {
+        let mut tag = Tag::new();
+        tag.set_title("Love Trip");
+        tag.extend((1..=3).map(|n| {
+            Frame::from(Picture {
+                mime_type: "image/png".to_string(),
+                picture_type: PictureType::Other,
+                description: format!("page {}", n),
+                data: vec![n],
+            })
+        }));
+        tag
+    }
+
+    #[test]
+    fn restore_repeated_keeps_every_picture_of_a_type() {
+        for version in [Version::Id3v22, Version::Id3v23, Version::Id3v24] {
+            let mut data = Vec::new();
+            pages().write_to(&mut data, version).unwrap();
+            let mut tag = Tag::read_from2(Cursor::new(&data)).unwrap();
+            restore_repeated(&mut tag, &data);
+
+            let pages: Vec<_> = tag.pictures().map(|p| p.description.as_str()).collect();
+            assert_eq!(pages, ["page 1", "page 2", "page 3"], "{}", version);
+            assert_eq!(tag.title(), Some("Love Trip"));
+        }
+    }
+
+    #[test]
+    fn syncsafe_round_trip() {
+        assert_eq!(syncsafe(&to_syncsafe(0x0FFF_FFFF)), 0x0FFF_FFFF);
+        assert_eq!(syncsafe(&to_syncsafe(300)), 300);
+    }
+}
@@ -0,0 +1,185 @@
+use id3::{Tag, TagLike};
+
+use crate::models::controls::ID3_GENRES;
+
+/// The genre names without the index `ID3_GENRES` prints them with.
+fn names() -> impl Iterator<Item = &'static str> {
+    ID3_GENRES
+        .iter()
+        .map(|g| g.split_once(": ").map_or(*g, |(_, name)| name))
+}
+
+/// The index of a genre in the ID3v1 list, ignoring case.
+pub fn index(name: &str) -> Option<u8> {
+    names()
+        .position(|g| g.eq_ignore_ascii_case(name.trim()))
+        .map(|i| i as u8)
+}
+
+/// The genre as the list spells it, or trimmed when it isn't in the list.
+pub fn canonical(name: &str) -> String {
+    match index(name) {
+        Some(i) => names().nth(i as usize).unwrap_or_default().to_string(),
+        None => name.trim().to_string(),
+    }
+}
+
+/// Parses the value of `-g`: an ID3v1 index like `17` or `(17)`, or a name. Names are spelled
+/// like the list, and a name close to one from the list is written as given with a warning, as
+/// it may be a typo.
+pub fn resolve(input: &str) -> Result<String, String> {
+    let trimmed = input.trim();
+    let number = trimmed
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or(trimmed);
+    if let Ok(i) = number.parse::<usize>() {
+        return names().nth(i).map(str::to_string).ok_or_else(|| {
+            format!(
+                "Unknown genre ID {}, use 'editag --list-genres' to see them",
+                i
+            )
+        });
+    }
+    if trimmed.is_empty() || index(trimmed).is_some() {
+        return Ok(canonical(trimmed));
+    }
+
+    let suggestions = suggest(trimmed);
+    if !suggestions.is_empty() {
+        eprintln!(
+            "Genre {:?} isn't in the list, did you mean {}?",
+            trimmed,
+            suggestions
+                .iter()
+                .map(|s| format!("{:?}", s))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        );
+    }
+    Ok(trimmed.to_string())
+}
+
+/// The genres of the tag with legacy references decoded and known names spelled like the list.
+pub fn normalized(tag: &Tag) -> Option<Vec<String>> {
+    let values: Vec<_> = tag
+        .genres()?
+        .into_iter()
+        .map(decode)
+        .filter(|g| !g.is_empty())
+        .collect();
+    (!values.is_empty()).then_some(values)
+}
+
+/// Decodes one TCON value. ID3v2.3 refers to genres by index as in `(17)`, optionally refined
+/// by a name following the references, `((` being a literal parenthesis. ID3v2.4 uses plain
+/// indexes like `17`. `RX` and `CR` stand for Remix and Cover.
+pub fn decode(value: &str) -> String {
+    let mut rest = value.trim();
+    let mut refs = Vec::new();
+    while let Some(inner) = rest.strip_prefix('(').filter(|r| !r.starts_with('(')) {
+        let Some((reference, after)) = inner.split_once(')').filter(|(r, _)| {
+            matches!(*r, "RX" | "CR") || (!r.is_empty() && r.chars().all(|c| c.is_ascii_digit()))
+        }) else {
+            break;
+        };
+        refs.push(reference);
+        rest = after;
+    }
+    if let Some(escaped) = rest.strip_prefix("((") {
+        return canonical(&format!("({}", escaped));
+    }
+    if refs.is_empty() {
+        refs.push(rest);
+    } else if !rest.trim().is_empty() {
+        return canonical(rest);
+    }
+
+    let names: Vec<_> = refs
+        .into_iter()
+        .map(|r| match r {
+            "RX" => "Remix".to_string(),
+            "CR" => "Cover".to_string(),
+            _ => r
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| names().nth(i))
+                .map_or_else(|| canonical(r), str::to_string),
+        })
+        .collect();
+    names.join("/")
+}
+
+/// Genres from the list within a couple of edits of the name, closest first.
+fn suggest(name: &str) -> Vec<&'static str> {
+    let name = name.to_lowercase();
+    let max = if name.chars().count() <= 4 { 1 } else { 2 };
+    let mut close: Vec<_> = names()
+        .map(|g| (distance(&name, &g.to_lowercase()), g))
+        .filter(|(d, _)| *d <= max)
+        .collect();
+    close.sort_by_key(|(d, _)| *d);
+    close.into_iter().take(3).map(|(_, g)| g).collect()
+}
+
+/// Edit distance between two strings counting a swap of neighbouring characters as one edit.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    d[0] = (0..=b.len()).collect();
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_legacy_references() {
+        assert_eq!(decode("(17)"), "Rock");
+        assert_eq!(decode("17"), "Rock");
+        assert_eq!(decode("(17)Rock"), "Rock");
+        assert_eq!(decode("(17)(24)"), "Rock/Soundtrack");
+        assert_eq!(decode("(RX)(CR)"), "Remix/Cover");
+        assert_eq!(decode("((Hed) P.E."), "(Hed) P.E.");
+        assert_eq!(decode("hip-hop"), "Hip-Hop");
+        assert_eq!(decode("City Pop"), "City Pop");
+    }
+
+    #[test]
+    fn resolve_ids_and_names() {
+        assert_eq!(resolve("17").as_deref(), Ok("Rock"));
+        assert_eq!(resolve("(17)").as_deref(), Ok("Rock"));
+        assert_eq!(resolve(" rock ").as_deref(), Ok("Rock"));
+        assert_eq!(resolve("City Pop").as_deref(), Ok("City Pop"));
+        assert!(resolve("999").is_err());
+    }
+
+    #[test]
+    fn resolve_keeps_names_close_to_listed_ones() {
+        assert_eq!(resolve("Trap").as_deref(), Ok("Trap"));
+        assert_eq!(resolve("K-Pop").as_deref(), Ok("K-Pop"));
+        assert_eq!(suggest("Rokc"), ["Rock"]);
+    }
+
+    #[test]
+    fn distance_counts_a_swap_as_one_edit() {
+        assert_eq!(distance("rokc", "rock"), 1);
+        assert_eq!(distance("rock", "rock"), 0);
+        assert_eq!(distance("trap", "rap"), 1);
+    }
+}
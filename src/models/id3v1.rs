@@ -0,0 +1,141 @@
+use std::{
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use id3::TagLike;
+
+use crate::models::{dates, genre, track::Track};
+
+pub const TAG_LEN: usize = 128;
+/// The Winamp extension that may precede the tag, `TAG+` followed by longer fields.
+const EXTENDED_LEN: usize = 227;
+
+/// What happens to the ID3v1 tag at the end of the file when it is saved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trailer {
+    Keep,
+    Write(Vec<u8>),
+    Strip,
+}
+
+/// The raw ID3v1 tag at the end of the file, with the extended tag in front of it if any.
+pub fn read_raw(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let len = file.seek(SeekFrom::End(0)).ok()? as usize;
+    let start = len.saturating_sub(TAG_LEN + EXTENDED_LEN);
+    file.seek(SeekFrom::Start(start as u64)).ok()?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).ok()?;
+
+    let tag = data.split_off(data.len().checked_sub(TAG_LEN)?);
+    if !tag.starts_with(b"TAG") {
+        return None;
+    }
+    if data.len() == EXTENDED_LEN && data.starts_with(b"TAG+") {
+        data.extend(tag);
+        Some(data)
+    } else {
+        Some(tag)
+    }
+}
+
+/// Whether `raw`, as returned by [`read_raw`], already holds `data`, the tag encoded for the
+/// track. An extended tag is never written, so a tag that has one is up to date when the values
+/// read from both still encode to `data`.
+pub fn up_to_date(raw: &[u8], track: &Track, data: &[u8]) -> bool {
+    if raw == data {
+        return true;
+    }
+    let Ok(v1) = id3::v1::Tag::read_from(Cursor::new(raw)) else {
+        return false;
+    };
+    let old = Track {
+        path: track.path.clone(),
+        tag: id3::Tag::from(v1),
+        original_version: None,
+        id3v1: None,
+        trailer: Trailer::Keep,
+    };
+    encode(&old).0 == data
+}
+
+/// Encodes the common fields of the tag as ID3v1.1, or ID3v1 when it has no track number.
+/// Returns the warnings for everything that had to be cut or left out.
+pub fn encode(track: &Track) -> (Vec<u8>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut data = b"TAG".to_vec();
+
+    let number = track.tag.track().and_then(|n| match u8::try_from(n) {
+        Ok(n) if n > 0 => Some(n),
+        _ => {
+            warnings.push(format!("ID3v1 can't hold track number {}, left out", n));
+            None
+        }
+    });
+    let comment_len = if number.is_some() { 28 } else { 30 };
+    let year = dates::recorded(&track.tag).map(|t| format!("{:04}", t.year));
+
+    for (name, value, len) in [
+        ("title", track.field("title"), 30),
+        ("artist", track.field("artist"), 30),
+        ("album", track.field("album"), 30),
+        ("year", year, 4),
+        ("comment", track.field("comment"), comment_len),
+    ] {
+        data.extend(latin1(name, &value.unwrap_or_default(), len, &mut warnings));
+    }
+    if let Some(n) = number {
+        data.extend([0, n]);
+    }
+
+    let genre = match genre::normalized(&track.tag).and_then(|g| g.into_iter().next()) {
+        Some(name) => genre::index(&name).unwrap_or_else(|| {
+            warnings.push(format!("ID3v1 has no genre {:?}, left out", name));
+            u8::MAX
+        }),
+        None => u8::MAX,
+    };
+    data.push(genre);
+
+    (data, warnings)
+}
+
+/// Replaces or removes the ID3v1 tag at the end of the file, along with its extended tag.
+pub fn write(file: &mut File, trailer: &Trailer) -> Result<(), String> {
+    if *trailer == Trailer::Keep {
+        return Ok(());
+    }
+    id3::v1::Tag::remove_from_file(&mut *file)
+        .map_err(|e| format!("Error removing ID3v1 tag :: {}", e))?;
+    if let Trailer::Write(data) = trailer {
+        file.seek(SeekFrom::End(0))
+            .and_then(|_| file.write_all(data))
+            .map_err(|e| format!("Error writing ID3v1 tag :: {}", e))?;
+    }
+    Ok(())
+}
+
+/// ID3v1 fields are fixed size ISO-8859-1, padded with nulls.
+fn latin1(name: &str, value: &str, len: usize, warnings: &mut Vec<String>) -> Vec<u8> {
+    let kept: String = value.chars().take(len).collect();
+    if kept.len() < value.len() && kept.chars().count() == len {
+        warnings.push(format!(
+            "ID3v1 {} cut to {} characters: {:?}",
+            name, len, kept
+        ));
+    }
+    if kept.chars().any(|c| u32::from(c) > 0xFF) {
+        warnings.push(format!(
+            "ID3v1 can't hold every character of the {} {:?}, replaced them with '?'",
+            name, kept
+        ));
+    }
+    let mut bytes: Vec<u8> = kept
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect();
+    bytes.resize(len, 0);
+    bytes
+}
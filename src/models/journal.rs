@@ -0,0 +1,290 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Cursor, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{frames, id3v1::Trailer, storage};
+
+const UNDONE_SUFFIX: &str = ".undone";
+/// Journals of earlier runs kept in the default directory. They hold whole tags, pictures
+/// included, so they are pruned rather than left to grow with every run.
+const KEPT_JOURNALS: usize = 20;
+
+/// One reversible step of a run. Tags are recorded before they are overwritten, renames and
+/// created files once they succeeded.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Entry {
+    /// The tag a file held before it was saved. `version` is `None` when it had no tag.
+    Tag {
+        path: PathBuf,
+        version: Option<u8>,
+        data: Option<String>,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// The ID3v1 tag a file held before it was rewritten or removed, `None` if it had none.
+    Trailer {
+        path: PathBuf,
+        data: Option<String>,
+    },
+    /// A file that didn't exist before the run, such as a copy.
+    Create {
+        path: PathBuf,
+    },
+}
+
+/// Append-only record of the changes made by a single run, used by `--undo`.
+pub struct Journal {
+    path: PathBuf,
+    file: Option<File>,
+    /// Set for journals in the default directory, whose older journals are pruned once this one
+    /// is created.
+    prune: bool,
+}
+
+impl Journal {
+    /// The file is only created once the first change is recorded, so runs that don't modify
+    /// anything leave no journal behind.
+    pub fn new(path: PathBuf) -> Self {
+        Journal {
+            path,
+            file: None,
+            prune: false,
+        }
+    }
+
+    /// A new journal named after the current time inside the default journal directory. Once it
+    /// is created, all but the most recent journals of earlier runs are removed.
+    pub fn for_this_run() -> Result<Self, String> {
+        let dir = default_dir().ok_or("Could not find a directory for the undo journal")?;
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        // Both parts are padded so the names sort by age, even for runs in the same second
+        let name = format!("{:012}-{:010}.jsonl", secs, process::id());
+        Ok(Journal {
+            prune: true,
+            ..Journal::new(dir.join(name))
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file.is_none()
+    }
+
+    pub fn record_tag(
+        &mut self,
+        path: &Path,
+        tag: &id3::Tag,
+        version: Option<id3::Version>,
+    ) -> Result<(), String> {
+        let data = match version {
+            Some(v) => {
+                let mut raw = Vec::new();
+                tag.write_to(&mut raw, v)
+                    .map_err(|e| format!("Error encoding previous tag :: {}", e))?;
+                Some(STANDARD.encode(raw))
+            }
+            None => None,
+        };
+        self.append(&Entry::Tag {
+            path: absolute(path),
+            version: version.map(|v| v.minor()),
+            data,
+        })
+    }
+
+    pub fn record_trailer(&mut self, path: &Path, raw: Option<&[u8]>) -> Result<(), String> {
+        self.append(&Entry::Trailer {
+            path: absolute(path),
+            data: raw.map(|r| STANDARD.encode(r)),
+        })
+    }
+
+    pub fn record_rename(&mut self, from: &Path, to: &Path) -> Result<(), String> {
+        self.append(&Entry::Rename {
+            from: absolute(from),
+            to: absolute(to),
+        })
+    }
+
+    pub fn record_create(&mut self, path: &Path) -> Result<(), String> {
+        self.append(&Entry::Create {
+            path: absolute(path),
+        })
+    }
+
+    fn append(&mut self, entry: &Entry) -> Result<(), String> {
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Error creating journal directory :: {}", e))?;
+                if self.prune {
+                    prune(parent);
+                }
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|e| format!("Error opening journal {:?} :: {}", self.path, e))?;
+            self.file = Some(file);
+        }
+        let file = self.file.as_mut().unwrap();
+
+        let line = serde_json::to_string(entry)
+            .map_err(|e| format!("Error encoding journal entry :: {}", e))?;
+        writeln!(file, "{}", line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| format!("Error writing journal :: {}", e))
+    }
+}
+
+/// Journals may be undone from another directory, so paths are stored absolute.
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// `$XDG_STATE_HOME/editag/journals`, falling back to `~/.local/state/editag/journals`.
+pub fn default_dir() -> Option<PathBuf> {
+    let state = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state.join("editag").join("journals"))
+}
+
+/// The most recent journal that hasn't been undone yet.
+pub fn latest() -> Option<PathBuf> {
+    let mut journals: Vec<PathBuf> = fs::read_dir(default_dir()?)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "jsonl"))
+        .collect();
+    journals.sort();
+    journals.pop()
+}
+
+/// Removes the oldest journals, undone or not, beyond [`KEPT_JOURNALS`]. Their names start with
+/// the time of the run, so they sort by age.
+fn prune(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut journals: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|e| e == "jsonl" || e == &UNDONE_SUFFIX[1..])
+        })
+        .collect();
+    journals.sort();
+    let excess = journals.len().saturating_sub(KEPT_JOURNALS);
+    for path in journals.drain(..excess) {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Error removing old journal {:?} :: {}", path, e);
+        }
+    }
+}
+
+/// Reverts every change in the journal, newest first, then marks the journal as undone.
+pub fn undo(journal: &Path) -> Result<(), String> {
+    let file =
+        File::open(journal).map_err(|e| format!("Error opening journal {:?} :: {}", journal, e))?;
+    let mut entries = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Error reading journal :: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid journal entry on line {} :: {}", n + 1, e))?;
+        entries.push(entry);
+    }
+
+    let mut failed = 0;
+    for entry in entries.iter().rev() {
+        if let Err(e) = revert(entry) {
+            eprintln!("{}", e);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!(
+            "{} change(s) could not be undone, the journal was kept",
+            failed
+        ));
+    }
+
+    let mut undone = journal.as_os_str().to_owned();
+    undone.push(UNDONE_SUFFIX);
+    fs::rename(journal, &undone)
+        .map_err(|e| format!("Error marking journal as undone :: {}", e))?;
+    Ok(())
+}
+
+fn revert(entry: &Entry) -> Result<(), String> {
+    match entry {
+        Entry::Rename { from, to } => {
+            storage::move_file(to, from)
+                .map_err(|e| format!("Error renaming {:?} back to {:?} :: {}", to, from, e))?;
+            println!("Renamed {:?} back to {:?}", to, from);
+        }
+        Entry::Create { path } => {
+            fs::remove_file(path).map_err(|e| format!("Error removing {:?} :: {}", path, e))?;
+            println!("Removed {:?}", path);
+        }
+        Entry::Trailer { path, data } => {
+            let trailer = match data {
+                Some(data) => Trailer::Write(
+                    STANDARD
+                        .decode(data)
+                        .map_err(|e| format!("Corrupt journal entry for {:?} :: {}", path, e))?,
+                ),
+                None => Trailer::Strip,
+            };
+            storage::write_trailer(path, &trailer)
+                .map_err(|e| format!("Error restoring ID3v1 tag of {:?} :: {}", path, e))?;
+            println!("Restored ID3v1 tag of {:?}", path);
+        }
+        Entry::Tag {
+            path,
+            version: Some(_),
+            data: Some(data),
+        } => {
+            let raw = STANDARD
+                .decode(data)
+                .map_err(|e| format!("Corrupt journal entry for {:?} :: {}", path, e))?;
+            let mut tag = id3::Tag::read_from2(Cursor::new(&raw))
+                .map_err(|e| format!("Corrupt journal entry for {:?} :: {}", path, e))?;
+            frames::restore_repeated(&mut tag, &raw);
+            storage::write_atomic(path, &tag, tag.version(), &Trailer::Keep)
+                .map_err(|e| format!("Error restoring tag of {:?} :: {}", path, e))?;
+            println!("Restored tag of {:?}", path);
+        }
+        Entry::Tag { path, .. } => {
+            id3::Tag::remove_from_path(path)
+                .map_err(|e| format!("Error removing tag of {:?} :: {}", path, e))?;
+            println!("Removed tag of {:?}, it had none before", path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+/// Reads lyrics text from a file, or from stdin when the source is `-`.
+pub fn read_source(source: &str) -> Result<String, String> {
+    let text = if source == "-" {
+        let mut buf = String::new();
+        io::stdin()
+            .read_to_string(&mut buf)
+            .map_err(|e| format!("Error reading lyrics from stdin :: {}", e))?;
+        buf
+    } else {
+        fs::read_to_string(source)
+            .map_err(|e| format!("Error reading lyrics file {:?} :: {}", source, e))?
+    };
+    Ok(text.replace("\r\n", "\n"))
+}
+
+/// Path of a sidecar file next to the track. A track with a single frame gets `track.<ext>`,
+/// otherwise the language and description are added so the files don't overwrite each other.
+/// Both are cleaned for file names, and a name already in `taken` is numbered like `-2`.
+pub fn sidecar_path(
+    track: &Path,
+    lang: &str,
+    description: &str,
+    single: bool,
+    ext: &str,
+    taken: &mut HashSet<PathBuf>,
+) -> PathBuf {
+    let stem = track
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("lyrics");
+
+    let clean = |s: &str| -> String {
+        s.chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect()
+    };
+    let base = if single {
+        stem.to_string()
+    } else if description.is_empty() {
+        format!("{}.{}", stem, clean(lang))
+    } else {
+        format!("{}.{}.{}", stem, clean(lang), clean(description))
+    };
+    numbered(track, &base, ext, taken)
+}
+
+/// `<base>.<ext>` next to the track, or `<base>-2.<ext>` and so on when the name is in `taken`.
+/// The returned path is added to `taken`.
+pub fn numbered(track: &Path, base: &str, ext: &str, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let mut out = track.with_file_name(format!("{}.{}", base, ext));
+    let mut n = 1;
+    while !taken.insert(out.clone()) {
+        n += 1;
+        out = track.with_file_name(format!("{}-{}.{}", base, n, ext));
+    }
+    out
+}
+
+/// Parses an LRC document into millisecond timestamped lines, sorted by time.
+///
+/// Lines may carry several timestamps (`[00:12.00][01:30.00]chorus`) and an `[offset:+/-ms]`
+/// tag shifts every timestamp, as players do. Other ID tags such as `[ar:...]` are ignored.
+pub fn parse_lrc(text: &str) -> Result<Vec<(u32, String)>, String> {
+    let mut offset: i64 = 0;
+    let mut lines = Vec::new();
+
+    for (n, line) in text.lines().enumerate() {
+        let mut rest = line.trim();
+        let mut stamps = Vec::new();
+
+        while let Some(inner) = rest.strip_prefix('[') {
+            let Some(end) = inner.find(']') else { break };
+            let tag = &inner[..end];
+            rest = &inner[end + 1..];
+
+            if let Some(ms) = parse_lrc_timestamp(tag) {
+                stamps.push(ms);
+            } else if let Some(v) = tag.strip_prefix("offset:") {
+                offset = v
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid LRC offset on line {} :: {:?}", n + 1, v))?;
+            }
+        }
+
+        for ms in stamps {
+            let shifted = (ms as i64).saturating_sub(offset).clamp(0, u32::MAX as i64) as u32;
+            lines.push((shifted, rest.to_string()));
+        }
+    }
+
+    if lines.is_empty() {
+        return Err("No timestamped lines found in LRC".to_string());
+    }
+    lines.sort_by_key(|(ms, _)| *ms);
+    Ok(lines)
+}
+
+/// Parses `mm:ss`, `mm:ss.f`, `mm:ss.ff`, `mm:ss.fff` or `mm:ss:ff` into milliseconds.
+fn parse_lrc_timestamp(tag: &str) -> Option<u32> {
+    let (minutes, rest) = tag.split_once(':')?;
+    let minutes: u32 = minutes.parse().ok()?;
+    let (seconds, fraction) = match rest.split_once(['.', ':']) {
+        Some((s, f)) => (s, f),
+        None => (rest, ""),
+    };
+    let seconds: u32 = seconds.parse().ok()?;
+    if seconds >= 60 || fraction.len() > 3 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let fraction: u32 = format!("{:0<3}", fraction).parse().ok()?;
+    minutes
+        .checked_mul(60_000)?
+        .checked_add(seconds * 1000 + fraction)
+}
+
+/// Formats timestamped lines as LRC, keeping millisecond precision when centiseconds would lose it.
+pub fn format_lrc(content: &[(u32, String)]) -> String {
+    let mut out = String::new();
+    for (ms, text) in content {
+        let minutes = ms / 60_000;
+        let seconds = (ms % 60_000) / 1000;
+        let millis = ms % 1000;
+        if millis % 10 == 0 {
+            out.push_str(&format!(
+                "[{:02}:{:02}.{:02}]",
+                minutes,
+                seconds,
+                millis / 10
+            ));
+        } else {
+            out.push_str(&format!("[{:02}:{:02}.{:03}]", minutes, seconds, millis));
+        }
+        out.push_str(text);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lrc_sorts_repeated_timestamps() {
+        let lines = parse_lrc("[ar:Takako Mamiya]\n[00:12.00][01:30.50]chorus\n[00:05.1]intro\n");
+        assert_eq!(
+            lines.unwrap(),
+            [
+                (5100, "intro".to_string()),
+                (12000, "chorus".to_string()),
+                (90500, "chorus".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_lrc_applies_the_offset() {
+        let lines = parse_lrc("[offset:+500]\n[00:00.20]a\n[00:01.000]b\n").unwrap();
+        assert_eq!(lines, [(0, "a".to_string()), (500, "b".to_string())]);
+    }
+
+    #[test]
+    fn parse_lrc_timestamp_formats() {
+        assert_eq!(parse_lrc_timestamp("01:02"), Some(62000));
+        assert_eq!(parse_lrc_timestamp("01:02.5"), Some(62500));
+        assert_eq!(parse_lrc_timestamp("01:02:25"), Some(62250));
+        assert_eq!(parse_lrc_timestamp("01:02.005"), Some(62005));
+        assert_eq!(parse_lrc_timestamp("01:60.00"), None);
+        assert_eq!(parse_lrc_timestamp("99999:00.00"), None);
+    }
+
+    #[test]
+    fn parse_lrc_needs_timestamped_lines() {
+        assert!(parse_lrc("[ar:Someone]\nno time\n").is_err());
+    }
+
+    #[test]
+    fn format_lrc_keeps_milliseconds() {
+        let text = format_lrc(&[(62500, "a".to_string()), (62005, "b".to_string())]);
+        assert_eq!(text, "[01:02.50]a\n[01:02.005]b\n");
+    }
+
+    #[test]
+    fn sidecar_path_numbers_names_already_taken() {
+        let track = Path::new("music/01.mp3");
+        let mut taken = HashSet::new();
+        let names: Vec<_> = [("eng", "Chorus!"), ("eng", "Chorus?"), ("e/g", "")]
+            .iter()
+            .map(|(lang, desc)| sidecar_path(track, lang, desc, false, "lrc", &mut taken))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                Path::new("music/01.eng.Chorus_.lrc"),
+                Path::new("music/01.eng.Chorus_-2.lrc"),
+                Path::new("music/01.e_g.lrc"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use id3::Timestamp;
+use serde_json::{Map, Value};
+
+use crate::models::{controls::ModifyAction, custom, dates, genre, values};
+
+/// Reads a JSON or YAML manifest mapping file paths or globs, relative to the manifest, to the
+/// frames they should carry. Entries are applied in document order, so a later entry overrides
+/// an earlier one matching the same file.
+///
+/// ```yaml
+/// "*.mp3":
+///   album: Love Trip
+///   year: 1982
+/// "01 - Love Trip.mp3":
+///   title: Love Trip
+///   track: 1
+///   txxx: { CATALOGNUMBER: VICL-61096 }
+/// ```
+pub fn load(path: &Path) -> Result<BTreeMap<PathBuf, Vec<ModifyAction>>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Error reading manifest {:?} :: {}", path, e))?;
+
+    let is_json = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let document: Value = if is_json {
+        serde_json::from_str(&text).map_err(|e| format!("Error parsing JSON manifest :: {}", e))?
+    } else {
+        serde_norway::from_str(&text)
+            .map_err(|e| format!("Error parsing YAML manifest :: {}", e))?
+    };
+
+    let entries = document
+        .as_object()
+        .ok_or("The manifest must map file paths to their frames")?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut files: BTreeMap<PathBuf, Vec<ModifyAction>> = BTreeMap::new();
+    for (pattern, fields) in entries {
+        let fields = fields
+            .as_object()
+            .ok_or_else(|| format!("Entry {:?} must map frame names to values", pattern))?;
+        let actions = entry_actions(fields, base_dir)
+            .map_err(|e| format!("Error in entry {:?} :: {}", pattern, e))?;
+
+        let matched = expand(base_dir, pattern)?;
+        if matched.is_empty() {
+            return Err(format!("No files match entry {:?}", pattern));
+        }
+        for file in matched {
+            files
+                .entry(file)
+                .or_default()
+                .extend(actions.iter().cloned());
+        }
+    }
+    Ok(files)
+}
+
+fn expand(base_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, String> {
+    // File names may well contain glob characters such as '[', so exact paths win
+    let exact = base_dir.join(pattern);
+    if exact.is_file() {
+        return Ok(vec![exact]);
+    }
+
+    let base = base_dir
+        .to_str()
+        .ok_or("Manifest paths must be valid UTF-8")?;
+    let full = if base.is_empty() {
+        pattern.to_string()
+    } else {
+        format!("{}/{}", glob::Pattern::escape(base), pattern)
+    };
+    let paths = glob::glob(&full).map_err(|e| format!("Invalid pattern {:?} :: {}", pattern, e))?;
+    Ok(paths.flatten().filter(|p| p.is_file()).collect())
+}
+
+fn entry_actions(
+    fields: &Map<String, Value>,
+    base_dir: &Path,
+) -> Result<Vec<ModifyAction>, String> {
+    let mut actions = Vec::new();
+    for (key, value) in fields {
+        actions.extend(field_actions(key, value, base_dir)?);
+    }
+    Ok(actions)
+}
+
+/// Turns one named field into actions. Besides the names below, `txxx:<desc>` and `wxxx:<desc>`
+/// set a single user frame, and any four character frame ID is written like `-C <id> <value>`.
+pub fn field_actions(
+    key: &str,
+    value: &Value,
+    base_dir: &Path,
+) -> Result<Vec<ModifyAction>, String> {
+    let action = match key {
+        "title" => ModifyAction::Title(string(key, value)?),
+        "album" => ModifyAction::Album(string(key, value)?),
+        "artist" => ModifyAction::Artist(strings(key, value)?),
+        "album_artist" => ModifyAction::AlbumArtist(string(key, value)?),
+        "genre" => ModifyAction::Genre(
+            strings(key, value)?
+                .iter()
+                .map(|g| genre::resolve(g))
+                .collect::<Result<_, _>>()?,
+        ),
+        "year" | "date" => ModifyAction::RecordingDate(timestamp(key, value)?),
+        "original_date" => ModifyAction::OriginalDate(timestamp(key, value)?),
+        "release_date" => ModifyAction::ReleaseDate(timestamp(key, value)?),
+        "track" => ModifyAction::TrackNumber(number(key, value)?),
+        "total_tracks" => ModifyAction::TotalTracks(Some(number(key, value)?)),
+        "disc" => ModifyAction::DiscNumber(number(key, value)?),
+        "total_discs" => ModifyAction::TotalDiscs(Some(number(key, value)?)),
+        "cover_art" => ModifyAction::CoverArt(base_dir.join(string(key, value)?)),
+        "comment" => ModifyAction::Comment {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: string(key, value)?,
+        },
+        "lyrics" => ModifyAction::Lyrics {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: string(key, value)?,
+        },
+        "txxx" | "wxxx" => {
+            let pairs = value
+                .as_object()
+                .ok_or_else(|| format!("{} must map descriptions to values", key))?;
+            return pairs
+                .iter()
+                .map(|(description, v)| {
+                    let v = string(description, v)?;
+                    Ok(if key == "txxx" {
+                        ModifyAction::UserText(description.clone(), v)
+                    } else {
+                        ModifyAction::UserLink(description.clone(), v)
+                    })
+                })
+                .collect();
+        }
+        "delete" => {
+            let ids = value
+                .as_array()
+                .ok_or("delete must be a list of frame IDs")?;
+            return ids
+                .iter()
+                .map(|id| Ok(ModifyAction::DeleteTag(string(key, id)?)))
+                .collect();
+        }
+        _ if key.starts_with("txxx:") => {
+            ModifyAction::UserText(key["txxx:".len()..].to_string(), string(key, value)?)
+        }
+        _ if key.starts_with("wxxx:") => {
+            ModifyAction::UserLink(key["wxxx:".len()..].to_string(), string(key, value)?)
+        }
+        id if id.len() == 4 => ModifyAction::Custom(custom::build_frame(id, &string(key, value)?)?),
+        _ => return Err(format!("Unknown field {:?}", key)),
+    };
+    Ok(vec![action])
+}
+
+fn string(key: &str, value: &Value) -> Result<String, String> {
+    match value {
+        // ID3 separates values with nulls, so one inside a value can't be written
+        Value::String(s) if s.contains('\0') => {
+            Err(format!("{} can't contain a null character", key))
+        }
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => Err(format!("{} must be a string", key)),
+    }
+}
+
+/// A list of strings, or a string holding several values separated by ';'.
+fn strings(key: &str, value: &Value) -> Result<Vec<String>, String> {
+    let values = match value {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| string(key, item))
+            .collect::<Result<_, _>>()?,
+        _ => values::from_cell(&string(key, value)?),
+    };
+    if values.is_empty() {
+        return Err(format!("{} must have a value", key));
+    }
+    Ok(values)
+}
+
+fn number<T: std::str::FromStr>(key: &str, value: &Value) -> Result<T, String> {
+    let text = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => String::new(),
+    };
+    text.parse()
+        .map_err(|_| format!("{} must be a number, got {}", key, value))
+}
+
+fn timestamp(key: &str, value: &Value) -> Result<Timestamp, String> {
+    dates::parse(&string(key, value)?)
+}
@@ -1,3 +1,23 @@
+pub mod artwork;
 pub mod controls;
+pub mod convert;
+pub mod cover;
+pub mod custom;
+pub mod dates;
+pub mod diff;
+pub mod filename;
+pub mod frames;
+pub mod genre;
+pub mod id3v1;
+pub mod journal;
+pub mod lyrics;
+pub mod manifest;
+pub mod organize;
+pub mod output;
 pub mod parser;
+pub mod storage;
+pub mod table;
+pub mod template;
 pub mod track;
+pub mod values;
+pub mod walker;
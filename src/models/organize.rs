@@ -0,0 +1,274 @@
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    models::{
+        cover,
+        journal::Journal,
+        storage,
+        template::{self, Template},
+        track::Track,
+        walker,
+    },
+    status,
+};
+
+pub const DEFAULT_LAYOUT: &str =
+    "{album_artist|artist}/[{year} - ]{album}/[{disc}-]{track:02} {title}";
+
+/// Album images copied into every directory tracks are organized into.
+const ALBUM_ART: [&str; 4] = ["cover", "folder", "front", "albumart"];
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+/// Sidecars holding lyrics, named `<track>[.<lang>[.<description>]].<ext>`.
+const LYRICS_EXTENSIONS: [&str; 2] = ["lrc", "txt"];
+/// Sidecars holding extracted pictures, named `<track>.<type>[-<n>].<ext>`.
+const PICTURE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "webp", "bmp", "tiff"];
+
+#[derive(Clone)]
+pub struct Organize {
+    pub dest: PathBuf,
+    pub layout: Template,
+    pub copy: bool,
+}
+
+impl Organize {
+    /// Where the track belongs below the destination. Every directory level of the layout is
+    /// cleaned like a file name.
+    pub fn target(&self, track: &Track) -> Result<PathBuf, String> {
+        let rendered = self.layout.render(track)?;
+        let ext = track
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("mp3");
+
+        let mut target = self.dest.clone();
+        let mut components = rendered.split('/').peekable();
+        while let Some(component) = components.next() {
+            let component = template::clean_name(component);
+            if component.is_empty() {
+                return Err(format!(
+                    "{:?} has an empty directory or file name",
+                    rendered
+                ));
+            }
+            if components.peek().is_some() {
+                target.push(component);
+            } else {
+                target.push(format!("{}.{}", component, ext));
+            }
+        }
+        Ok(target)
+    }
+
+    /// Moves or copies the track, its sidecars and the album art next to it into place.
+    /// Nothing is touched if any target already exists, and `claimed` catches tracks of the same
+    /// run that would land on the same path. A move that still fails, like a full disk, stops
+    /// at that file: the ones already moved stay there, recorded in the journal, and the track's
+    /// path follows it as soon as it has moved.
+    pub fn run(
+        &self,
+        track: &mut Track,
+        claimed: &mut HashSet<PathBuf>,
+        journal: &mut Option<Journal>,
+        dry_run: bool,
+    ) -> Result<(), String> {
+        let target = self.target(track)?;
+        if target == track.path {
+            return Ok(());
+        }
+
+        let mut moves = vec![(track.path.clone(), target.clone())];
+        for (sidecar, suffix) in sidecars(&track.path) {
+            let mut name = target.file_stem().unwrap_or_default().to_os_string();
+            name.push(suffix);
+            moves.push((sidecar, target.with_file_name(name)));
+        }
+        for (_, to) in &moves {
+            if claimed.contains(to) {
+                return Err(format!("{:?} is also the target of another file", to));
+            }
+            if to.exists() {
+                return Err(format!("{:?} already exists", to));
+            }
+        }
+        claimed.extend(moves.iter().map(|(_, to)| to.clone()));
+
+        let target_dir = target.parent().unwrap_or(Path::new(""));
+        let art: Vec<_> = album_art(track.path.parent().unwrap_or(Path::new(".")))
+            .into_iter()
+            .filter_map(|image| {
+                let to = target_dir.join(image.file_name()?);
+                (!to.exists() && claimed.insert(to.clone())).then_some((image, to))
+            })
+            .collect();
+
+        let verb = if self.copy { "Copy" } else { "Move" };
+        if dry_run {
+            for (from, to) in &moves {
+                status!("  {} {:?} -> {:?}", verb, from, to);
+            }
+            for (from, to) in &art {
+                status!("  Copy {:?} -> {:?}", from, to);
+            }
+            return Ok(());
+        }
+
+        for (from, to) in &moves {
+            if self.copy {
+                storage::copy_file(from, to)?;
+                record(journal, |j| j.record_create(to));
+                status!("Copied {:?} to {:?}", from, to);
+            } else {
+                storage::move_file(from, to)?;
+                record(journal, |j| j.record_rename(from, to));
+                status!("Moved {:?} to {:?}", from, to);
+                if *from == track.path {
+                    track.path = to.clone();
+                }
+            }
+        }
+        for (from, to) in &art {
+            storage::copy_file(from, to)?;
+            record(journal, |j| j.record_create(to));
+            status!("Copied {:?} to {:?}", from, to);
+        }
+
+        Ok(())
+    }
+}
+
+fn record(journal: &mut Option<Journal>, op: impl FnOnce(&mut Journal) -> Result<(), String>) {
+    if let Some(journal) = journal {
+        if let Err(e) = op(journal) {
+            eprintln!("Failed to record change: {}", e);
+        }
+    }
+}
+
+/// Files next to the track named after it the way editag exports them, like `track.lrc`,
+/// `track.eng.txt` or `track.back-cover.jpg`, with the part of their name following the track's
+/// stem. Other files sharing the prefix, like `01.5 Interlude.lrc` next to `01.mp3`, belong to
+/// other tracks.
+fn sidecars(track: &Path) -> Vec<(PathBuf, String)> {
+    let (Some(dir), Some(stem)) = (track.parent(), track.file_stem().and_then(OsStr::to_str))
+    else {
+        return Vec::new();
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let prefix = format!("{}.", stem);
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<_> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && !walker::is_mp3(p))
+        .filter_map(|p| {
+            let suffix = p.file_name()?.to_str()?.strip_prefix(&prefix)?.to_string();
+            is_sidecar(&suffix).then(|| (p, format!(".{}", suffix)))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// Whether the name following the track's stem and a dot is one of an exported sidecar.
+fn is_sidecar(suffix: &str) -> bool {
+    let mut parts: Vec<&str> = suffix.split('.').collect();
+    let Some(ext) = parts.pop().map(str::to_ascii_lowercase) else {
+        return false;
+    };
+    // Files named like an earlier one of the same export are numbered, as in `eng.Chorus-2.lrc`
+    if let Some(last) = parts.last_mut() {
+        if let Some((base, n)) = last.rsplit_once('-') {
+            if n.parse::<u32>().is_ok() {
+                *last = base;
+            }
+        }
+    }
+    let middle = parts.as_slice();
+    if LYRICS_EXTENSIONS.contains(&ext.as_str()) {
+        return match middle {
+            [] => true,
+            [lang, description @ ..] => {
+                lang.chars().count() == 3
+                    && lang.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    && description.len() <= 1
+                    && description.iter().all(|d| {
+                        !d.is_empty() && d.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    })
+            }
+        };
+    }
+    if PICTURE_EXTENSIONS.contains(&ext.as_str()) {
+        if let [name] = middle {
+            return name.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+                && cover::picture_type(name).is_ok();
+        }
+    }
+    false
+}
+
+fn album_art(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<_> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            let stem = p.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+            let ext = p.extension().and_then(OsStr::to_str).unwrap_or_default();
+            p.is_file()
+                && ALBUM_ART.iter().any(|a| stem.eq_ignore_ascii_case(a))
+                && IMAGE_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecars_are_the_exported_names() {
+        for suffix in [
+            "lrc",
+            "txt",
+            "eng.lrc",
+            "jpn.Romaji_2.txt",
+            "eng-2.lrc",
+            "eng.Chorus-3.lrc",
+            "front-cover.jpg",
+            "back-cover-2.PNG",
+        ] {
+            assert!(is_sidecar(suffix), "{}", suffix);
+        }
+    }
+
+    #[test]
+    fn files_of_other_tracks_are_not_sidecars() {
+        for suffix in [
+            "5 Interlude.lrc",
+            "5.lrc",
+            "bonus.jpg",
+            "eng.a.b.lrc",
+            "nfo",
+            "cue",
+        ] {
+            assert!(!is_sidecar(suffix), "{}", suffix);
+        }
+    }
+}
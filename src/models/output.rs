@@ -0,0 +1,185 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use id3::frame::{Content, Picture, TimestampFormat};
+use serde_json::{json, Value};
+
+use crate::models::{genre, track::Track};
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Ndjson,
+}
+
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+static IN_ARRAY: AtomicBool = AtomicBool::new(false);
+static FIRST_ITEM: AtomicBool = AtomicBool::new(true);
+
+/// Keeps stdout clean for machine readable output by sending progress messages to stderr.
+pub fn send_status_to_stderr() {
+    STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+pub fn status_to_stderr() -> bool {
+    STATUS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// Progress messages, printed to stdout unless it carries JSON.
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::models::output::status_to_stderr() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Wraps every document printed until [`end_array`] in a single JSON array.
+pub fn begin_array() {
+    IN_ARRAY.store(true, Ordering::Relaxed);
+    println!("[");
+}
+
+pub fn end_array() {
+    if !FIRST_ITEM.load(Ordering::Relaxed) {
+        println!();
+    }
+    println!("]");
+}
+
+pub fn emit(value: &Value, format: OutputFormat) {
+    match format {
+        OutputFormat::Ndjson => println!("{}", value),
+        _ => {
+            let pretty = serde_json::to_string_pretty(value).unwrap_or_default();
+            if !IN_ARRAY.load(Ordering::Relaxed) {
+                println!("{}", pretty);
+            } else if FIRST_ITEM.swap(false, Ordering::Relaxed) {
+                print!("{}", pretty);
+            } else {
+                print!(",\n{}", pretty);
+            }
+        }
+    }
+}
+
+pub fn track_json(track: &Track, include_pictures: bool) -> Value {
+    let mut frames: Vec<_> = track.tag.frames().collect();
+    frames.sort_by(|a, b| a.id().cmp(b.id()));
+
+    json!({
+        "path": track.path,
+        "version": track.original_version.map(|v| v.to_string()),
+        "frames": frames
+            .iter()
+            .map(|f| json!({
+                "id": f.id(),
+                "name": f.name(),
+                "content": match f.id() {
+                    "TCON" => json!({
+                        "type": "text",
+                        "values": genre::normalized(&track.tag),
+                    }),
+                    _ => content_json(f.content(), include_pictures),
+                },
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn binary(data: &[u8], include: bool) -> Value {
+    if include {
+        Value::String(STANDARD.encode(data))
+    } else {
+        Value::Null
+    }
+}
+
+fn picture_json(p: &Picture, include_data: bool) -> Value {
+    json!({
+        "type": "picture",
+        "picture_type": p.picture_type.to_string(),
+        "mime_type": p.mime_type,
+        "description": p.description,
+        "size": p.data.len(),
+        "data": binary(&p.data, include_data),
+    })
+}
+
+fn content_json(content: &Content, include_pictures: bool) -> Value {
+    match content {
+        Content::Text(_) => json!({
+            "type": "text",
+            "values": content.text_values().map(|v| v.collect::<Vec<_>>()),
+        }),
+        Content::Link(url) => json!({ "type": "link", "url": url }),
+        Content::ExtendedText(t) => json!({
+            "type": "extended_text",
+            "description": t.description,
+            "value": t.value,
+        }),
+        Content::ExtendedLink(l) => json!({
+            "type": "extended_link",
+            "description": l.description,
+            "url": l.link,
+        }),
+        Content::Comment(c) => json!({
+            "type": "comment",
+            "lang": c.lang,
+            "description": c.description,
+            "text": c.text,
+        }),
+        Content::Lyrics(l) => json!({
+            "type": "lyrics",
+            "lang": l.lang,
+            "description": l.description,
+            "text": l.text,
+        }),
+        Content::SynchronisedLyrics(l) => json!({
+            "type": "synchronised_lyrics",
+            "lang": l.lang,
+            "description": l.description,
+            "content_type": l.content_type.to_string(),
+            "timestamp_format": match l.timestamp_format {
+                TimestampFormat::Ms => "ms",
+                TimestampFormat::Mpeg => "mpeg_frames",
+            },
+            "lines": l
+                .content
+                .iter()
+                .map(|(time, text)| json!({ "time": time, "text": text }))
+                .collect::<Vec<_>>(),
+        }),
+        Content::Picture(p) => picture_json(p, include_pictures),
+        Content::Popularimeter(p) => json!({
+            "type": "popularimeter",
+            "user": p.user,
+            "rating": p.rating,
+            "counter": p.counter,
+        }),
+        Content::UniqueFileIdentifier(u) => json!({
+            "type": "unique_file_identifier",
+            "owner": u.owner_identifier,
+            "identifier": String::from_utf8_lossy(&u.identifier),
+        }),
+        Content::EncapsulatedObject(o) => json!({
+            "type": "encapsulated_object",
+            "mime_type": o.mime_type,
+            "filename": o.filename,
+            "description": o.description,
+            "size": o.data.len(),
+            "data": binary(&o.data, include_pictures),
+        }),
+        Content::Private(p) => json!({
+            "type": "private",
+            "owner": p.owner_identifier,
+            "size": p.private_data.len(),
+            "data": binary(&p.private_data, include_pictures),
+        }),
+        other => json!({ "type": "other", "display": other.to_string() }),
+    }
+}
@@ -1,14 +1,27 @@
 use std::path::PathBuf;
 
-use clap::{command, Arg, ArgAction, ArgMatches};
+use clap::{command, error::ErrorKind, Arg, ArgAction, ArgMatches};
+use glob::Pattern;
+use id3::Timestamp;
 
-use crate::models::controls::{AppConfig, ChangeSet, ModifyAction};
+use crate::models::{
+    artwork::ExtractArt,
+    controls::{AppConfig, Backup, ChangeSet, Id3v1Mode, JournalMode, ModifyAction, VersionPolicy},
+    cover::{self, CoverFormat, CoverOptions, PictureSelector},
+    custom, dates,
+    filename::FilenamePattern,
+    genre, lyrics,
+    organize::{self, Organize},
+    output::OutputFormat,
+    table,
+    template::Template,
+};
 
 pub struct MyParser;
 
 impl MyParser {
     pub fn generate_commandline_args() -> ArgMatches {
-        let matches = command!()
+        command!()
             .arg(Arg::new("file_path").action(ArgAction::Set))
             .arg(
                 Arg::new("title")
@@ -28,8 +41,22 @@ impl MyParser {
                 Arg::new("artist")
                     .short('A')
                     .long("artist")
-                    .help("Set the artist")
-                    .action(ArgAction::Set),
+                    .help("Set the artist, repeat for several artists")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("add-artist")
+                    .long("add-artist")
+                    .help("Add an artist to the existing ones")
+                    .conflicts_with("artist")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("remove-artist")
+                    .long("remove-artist")
+                    .help("Remove one artist, keeping the others")
+                    .conflicts_with("artist")
+                    .action(ArgAction::Append),
             )
             .arg(
                 Arg::new("album-artist")
@@ -42,8 +69,25 @@ impl MyParser {
                 Arg::new("year")
                     .short('y')
                     .long("year")
-                    .help("Set the year")
-                    .value_parser(clap::value_parser!(i32))
+                    .value_name("date")
+                    .help("Set the recording date, a year or an ISO 8601 date such as 1982-05-21T14:30")
+                    .value_parser(dates::parse)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("original-date")
+                    .long("original-date")
+                    .value_name("date")
+                    .help("Set the original release date (TDOR, TORY in ID3v2.3)")
+                    .value_parser(dates::parse)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("release-date")
+                    .long("release-date")
+                    .value_name("date")
+                    .help("Set the release date (TDRL)")
+                    .value_parser(dates::parse)
                     .action(ArgAction::Set),
             )
             .arg(
@@ -54,18 +98,153 @@ impl MyParser {
                     .value_parser(clap::value_parser!(u32))
                     .action(ArgAction::Set),
             )
+            .arg(
+                Arg::new("total-tracks")
+                    .long("total-tracks")
+                    .help("Set the number of tracks on the disc")
+                    .value_parser(clap::value_parser!(u32))
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("disc")
+                    .long("disc")
+                    .help("Set the disc number")
+                    .value_parser(clap::value_parser!(u32))
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("total-discs")
+                    .long("total-discs")
+                    .help("Set the number of discs")
+                    .value_parser(clap::value_parser!(u32))
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("auto-number")
+                    .long("auto-number")
+                    .help("Number tracks and set the total by file name order within each directory")
+                    .conflicts_with_all(["track_number", "total-tracks"])
+                    .action(ArgAction::SetTrue),
+            )
             .arg(
                 Arg::new("genre")
                     .short('g')
                     .long("genre")
-                    .help("Set the genre")
+                    .help("Set the genre, by name or by its ID from --list-genres, repeat for several genres")
+                    .value_parser(genre::resolve)
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("add-genre")
+                    .long("add-genre")
+                    .help("Add a genre to the existing ones")
+                    .value_parser(genre::resolve)
+                    .conflicts_with("genre")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("remove-genre")
+                    .long("remove-genre")
+                    .help("Remove one genre, keeping the others")
+                    .value_parser(genre::resolve)
+                    .conflicts_with("genre")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("split")
+                    .long("split")
+                    .value_name("separator")
+                    .help("Split artists and genres into several values on a separator like ';' or 'feat.', ignoring case")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("separator")
+                    .long("separator")
+                    .help("Join several values with this when saving ID3v2.3 or ID3v2.2 tags")
+                    .default_value("/")
                     .action(ArgAction::Set),
             )
+            .arg(
+                Arg::new("normalize-genres")
+                    .long("normalize-genres")
+                    .help("Rewrite '(17)' style genres as names and fix the spelling of known genres")
+                    .conflicts_with("genre")
+                    .action(ArgAction::SetTrue),
+            )
             .arg(
                 Arg::new("cover-art-path")
                     .short('c')
                     .long("cover-art")
-                    .help("Set the cover art\n")
+                    .help("Set the cover art")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("picture")
+                    .long("picture")
+                    .num_args(2)
+                    .value_names(["type", "path"])
+                    .help("Add a picture of the given type, like back-cover or artist, replacing the one already there (repeatable)")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("picture-desc")
+                    .long("picture-desc")
+                    .value_name("description")
+                    .help("Description of the pictures added with --picture")
+                    .requires("picture")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("remove-picture")
+                    .long("remove-picture")
+                    .value_name("type|number")
+                    .help("Remove the pictures of a type, or a picture by its number in --list-pictures (repeatable)")
+                    .value_parser(cover::picture_selector)
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("list-pictures")
+                    .long("list-pictures")
+                    .help("List the embedded pictures with their numbers")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("cover-raw")
+                    .long("cover-raw")
+                    .help("Embed the cover art file as it is instead of re-encoding it")
+                    .conflicts_with_all(["cover-format", "cover-quality", "cover-max-size", "cover-budget"])
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("cover-format")
+                    .long("cover-format")
+                    .help("Format of the embedded cover art, auto keeps JPEG and PNG files untouched when they fit")
+                    .value_parser(clap::value_parser!(CoverFormat))
+                    .default_value("jpeg")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("cover-quality")
+                    .long("cover-quality")
+                    .help("JPEG quality of the embedded cover art, from 1 to 100")
+                    .value_parser(clap::value_parser!(u8).range(1..=100))
+                    .default_value("75")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("cover-max-size")
+                    .long("cover-max-size")
+                    .value_name("pixels")
+                    .help("Scale down cover art whose width or height exceeds this")
+                    .value_parser(clap::value_parser!(u32).range(1..))
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("cover-budget")
+                    .long("cover-budget")
+                    .value_name("KB")
+                    .help("Largest embedded cover art, reached by falling back to JPEG, lowering the quality, then scaling down\n")
+                    .value_parser(clap::value_parser!(u32).range(1..))
                     .action(ArgAction::Set),
             )
             .arg(
@@ -75,6 +254,21 @@ impl MyParser {
                     .help("View files existing id3 data")
                     .action(ArgAction::SetTrue),
             )
+            .arg(
+                Arg::new("output")
+                    .short('o')
+                    .long("output")
+                    .help("Format of --print-data, ndjson prints one line per file")
+                    .value_parser(clap::value_parser!(OutputFormat))
+                    .default_value("table")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("include-pictures")
+                    .long("include-pictures")
+                    .help("Include base64 picture and other binary data in JSON output")
+                    .action(ArgAction::SetTrue),
+            )
             .arg(
                 Arg::new("list-genres")
                     .long("list-genres")
@@ -101,28 +295,342 @@ impl MyParser {
                     .help("Run command on every mp3 file in the specified directory or current directory if none is provided")
                     .action(ArgAction::SetTrue)
             )
+            .arg(
+                Arg::new("import")
+                    .long("import")
+                    .value_name("manifest")
+                    .help("Apply the frames listed per file or glob in a JSON or YAML manifest")
+                    .conflicts_with("file_path")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("export-csv")
+                    .long("export-csv")
+                    .value_name("file")
+                    .help("Write the fields of every file found to a CSV, one row per file")
+                    .conflicts_with_all(["import", "import-csv"])
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("csv-fields")
+                    .long("csv-fields")
+                    .value_name("fields")
+                    .help("Comma separated columns for --export-csv: field names, txxx:<desc>, wxxx:<desc> or frame IDs")
+                    .requires("export-csv")
+                    .value_delimiter(',')
+                    .value_parser(parse_csv_field)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("import-csv")
+                    .long("import-csv")
+                    .value_name("file")
+                    .help("Apply the cells of an edited CSV that differ from the files")
+                    .conflicts_with_all(["file_path", "import"])
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("max-depth")
+                    .long("max-depth")
+                    .help("Limit how many directory levels -r descends (0 = only the given directory)")
+                    .value_parser(clap::value_parser!(usize))
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("follow-symlinks")
+                    .long("follow-symlinks")
+                    .help("Follow symlinked files and directories when using -r")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("hidden")
+                    .long("hidden")
+                    .help("Include hidden files and directories when using -r")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("include")
+                    .long("include")
+                    .value_name("pattern")
+                    .help("Only process files matching the glob pattern (repeatable)")
+                    .value_parser(parse_pattern)
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("exclude")
+                    .long("exclude")
+                    .value_name("pattern")
+                    .help("Skip files and directories matching the glob pattern (repeatable)")
+                    .value_parser(parse_pattern)
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("from-filename")
+                    .long("from-filename")
+                    .value_name("pattern")
+                    .help("Set fields parsed from the file path, e.g. '%artist%/%album%/%track% - %title%.mp3'")
+                    .value_parser(FilenamePattern::parse)
+                    .action(ArgAction::Set),
+            )
             .arg(
                 Arg::new("format-file")
                     .short('f')
                     .long("format-file")
-                    .help("Format file to 'TN - TRACK TITLE' TN = track number\n")
+                    .help("Rename the file from its tag, '{track:02} - {title}' unless --template is given")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("template")
+                    .long("template")
+                    .value_name("template")
+                    .help("File name template for -f, e.g. '{track:02} - [{disc}-]{album_artist|artist} - {title}'\n")
+                    .requires("format-file")
+                    .value_parser(Template::parse)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("organize")
+                    .long("organize")
+                    .value_name("dir")
+                    .help("Move files into a directory tree built from their tags, with their sidecar files")
+                    .conflicts_with("format-file")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("layout")
+                    .long("layout")
+                    .value_name("template")
+                    .help("Path template for --organize [default: '{album_artist|artist}/[{year} - ]{album}/[{disc}-]{track:02} {title}']")
+                    .requires("organize")
+                    .value_parser(Template::parse)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("copy")
+                    .long("copy")
+                    .help("Copy files with --organize instead of moving them\n")
+                    .requires("organize")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("convert")
+                    .long("convert")
+                    .help("Rewrite the tag for the version being saved, reporting frames it can't hold")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("v1")
+                    .long("v1")
+                    .help("Also write an ID3v1.1 tag at the end of every file that is saved")
+                    .conflicts_with_all(["sync-v1", "strip-v1"])
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("sync-v1")
+                    .long("sync-v1")
+                    .help("Copy the tag into the ID3v1.1 tag of every file where it differs")
+                    .conflicts_with("strip-v1")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("strip-v1")
+                    .long("strip-v1")
+                    .help("Remove the ID3v1 tag at the end of the file")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("v24")
+                    .long("v24")
+                    .help("Save tags as ID3v2.4 instead of the version each file already has")
+                    .conflicts_with_all(["v23", "v22"])
                     .action(ArgAction::SetTrue),
             )
             .arg(
                 Arg::new("v23")
                     .long("v23")
-                    .help("Attempts to save tag as ID3v2.3 instead of ID3v2.4")
+                    .help("Save tags as ID3v2.3 instead of the version each file already has")
+                    .conflicts_with("v22")
                     .action(ArgAction::SetTrue),
             )
             .arg(
                 Arg::new("v22")
                     .long("v22")
-                    .help("Attempts to save tag as ID3v2.2 instead of ID3v2.4\n")
+                    .help("Save tags as ID3v2.2 instead of the version each file already has")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("dry-run")
+                    .long("dry-run")
+                    .help("Show the frames and file names that would change without saving anything")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("backup")
+                    .long("backup")
+                    .help("Keep a .bak copy of each file before saving changes")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("backup-dir")
+                    .long("backup-dir")
+                    .value_name("dir")
+                    .help("Keep a copy of each file in the given directory before saving changes")
+                    .conflicts_with("backup")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("journal")
+                    .long("journal")
+                    .value_name("file")
+                    .help("Record changes for --undo in the given file instead of the default journal directory")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("no-journal")
+                    .long("no-journal")
+                    .help("Don't record changes for --undo")
+                    .conflicts_with("journal")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("undo")
+                    .long("undo")
+                    .value_name("journal")
+                    .num_args(0..=1)
+                    .default_missing_value("")
+                    .help("Restore the tags and file names changed by a run, the most recent one if no journal is given (ignores other flags)\n")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("comment")
+                    .long("comment")
+                    .value_name("text")
+                    .help("Add a comment, replacing any with the same language and description")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("comment-desc")
+                    .long("comment-desc")
+                    .value_name("description")
+                    .help("Description of the comment to set or remove (default: empty)")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("comment-lang")
+                    .long("comment-lang")
+                    .value_name("lang")
+                    .help("ISO-639-2 language code of the comment (default: eng)")
+                    .value_parser(parse_lang)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("remove-comment")
+                    .long("remove-comment")
+                    .help("Remove the comment matching --comment-desc (and --comment-lang if given)\n")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("lyrics")
+                    .long("lyrics")
+                    .value_name("file")
+                    .help("Set unsynchronised lyrics (USLT) from a text file, or '-' for stdin")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("lyrics-desc")
+                    .long("lyrics-desc")
+                    .value_name("description")
+                    .help("Description of the lyrics (default: empty)")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("lyrics-lang")
+                    .long("lyrics-lang")
+                    .value_name("lang")
+                    .help("ISO-639-2 language code of the lyrics (default: eng)")
+                    .value_parser(parse_lang)
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("export-lyrics")
+                    .long("export-lyrics")
+                    .help("Write existing USLT lyrics to .txt files next to each track")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("overwrite")
+                    .long("overwrite")
+                    .help("Replace existing files with --export-lyrics and --export-lrc")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("lrc")
+                    .long("lrc")
+                    .value_name("file")
+                    .help("Set synchronised lyrics (SYLT) from an LRC file, or '-' for stdin")
+                    .conflicts_with("auto-lrc")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("auto-lrc")
+                    .long("auto-lrc")
+                    .help("Set synchronised lyrics from the .lrc file sharing each track's name")
                     .action(ArgAction::SetTrue),
             )
-            .arg(Arg::new("custom-flag").short('C').long("custom").num_args(2).value_names(["Frame id", "Value"]).help("Set a custom frame and its value"))
-            .get_matches();
-        matches
+            .arg(
+                Arg::new("export-lrc")
+                    .long("export-lrc")
+                    .help("Write existing SYLT lyrics to .lrc files next to each track")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("extract-art")
+                    .long("extract-art")
+                    .value_name("mode")
+                    .help("Write embedded pictures next to each track, or a single cover per directory whose tracks share it with 'album'\n")
+                    .value_parser(clap::value_parser!(ExtractArt))
+                    .num_args(0..=1)
+                    .default_missing_value("tracks")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("txxx")
+                    .long("txxx")
+                    .num_args(2)
+                    .value_names(["description", "value"])
+                    .help("Set a user defined text frame (TXXX), e.g. --txxx CATALOGNUMBER VICL-123 (repeatable)")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("wxxx")
+                    .long("wxxx")
+                    .num_args(2)
+                    .value_names(["description", "url"])
+                    .help("Set a user defined link frame (WXXX) (repeatable)")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("remove-txxx")
+                    .long("remove-txxx")
+                    .value_name("description")
+                    .help("Remove the TXXX frame with the given description (repeatable)")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("remove-wxxx")
+                    .long("remove-wxxx")
+                    .value_name("description")
+                    .help("Remove the WXXX frame with the given description (repeatable)")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("list-user-frames")
+                    .long("list-user-frames")
+                    .help("List TXXX and WXXX frames with their descriptions\n")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(Arg::new("custom-flag").short('C').long("custom").num_args(2).value_names(["Frame id", "Value"]).help("Set a custom frame and its value, structured frames take '|' separated fields"))
+            .get_matches()
     }
 
     pub fn parse_command(matches: &ArgMatches) -> (ChangeSet, AppConfig) {
@@ -131,8 +639,24 @@ impl MyParser {
         if let Some(v) = matches.get_one::<String>("title") {
             actions.push(ModifyAction::Title(v.clone()));
         }
-        if let Some(v) = matches.get_one::<String>("artist") {
-            actions.push(ModifyAction::Artist(v.clone()));
+        if let Some(v) = matches.get_many::<String>("artist") {
+            actions.push(ModifyAction::Artist(v.cloned().collect()));
+        }
+        for (arg, id) in [("add-artist", "TPE1"), ("add-genre", "TCON")] {
+            for v in matches.get_many::<String>(arg).into_iter().flatten() {
+                actions.push(ModifyAction::AddValue {
+                    id: id.to_string(),
+                    value: v.clone(),
+                });
+            }
+        }
+        for (arg, id) in [("remove-artist", "TPE1"), ("remove-genre", "TCON")] {
+            for v in matches.get_many::<String>(arg).into_iter().flatten() {
+                actions.push(ModifyAction::RemoveValue {
+                    id: id.to_string(),
+                    value: v.clone(),
+                });
+            }
         }
         if let Some(v) = matches.get_one::<String>("album") {
             actions.push(ModifyAction::Album(v.clone()));
@@ -140,35 +664,194 @@ impl MyParser {
         if let Some(v) = matches.get_one::<String>("album-artist") {
             actions.push(ModifyAction::AlbumArtist(v.clone()));
         }
-        if let Some(v) = matches.get_one::<String>("genre") {
-            actions.push(ModifyAction::Genre(v.clone()));
+        if let Some(v) = matches.get_many::<String>("genre") {
+            actions.push(ModifyAction::Genre(v.cloned().collect()));
+        }
+        if matches.get_flag("normalize-genres") {
+            actions.push(ModifyAction::NormalizeGenre);
+        }
+        if let Some(v) = matches.get_one::<Timestamp>("year") {
+            actions.push(ModifyAction::RecordingDate(*v));
+        }
+        if let Some(v) = matches.get_one::<Timestamp>("original-date") {
+            actions.push(ModifyAction::OriginalDate(*v));
         }
-        if let Some(v) = matches.get_one::<i32>("year") {
-            actions.push(ModifyAction::Year(*v));
+        if let Some(v) = matches.get_one::<Timestamp>("release-date") {
+            actions.push(ModifyAction::ReleaseDate(*v));
         }
         if let Some(v) = matches.get_one::<u32>("track_number") {
             actions.push(ModifyAction::TrackNumber(*v));
         }
+        if let Some(v) = matches.get_one::<u32>("total-tracks") {
+            actions.push(ModifyAction::TotalTracks(Some(*v)));
+        }
+        if let Some(v) = matches.get_one::<u32>("disc") {
+            actions.push(ModifyAction::DiscNumber(*v));
+        }
+        if let Some(v) = matches.get_one::<u32>("total-discs") {
+            actions.push(ModifyAction::TotalDiscs(Some(*v)));
+        }
+        if let Some(selectors) = matches.get_many::<PictureSelector>("remove-picture") {
+            actions.push(ModifyAction::RemovePictures(selectors.cloned().collect()));
+        }
         if let Some(v) = matches.get_one::<String>("cover-art-path") {
             actions.push(ModifyAction::CoverArt(PathBuf::from(v)));
         }
+        let picture_desc = matches
+            .get_one::<String>("picture-desc")
+            .cloned()
+            .unwrap_or_default();
+        if let Some(occurrences) = matches.get_occurrences::<String>("picture") {
+            for mut values in occurrences {
+                let picture_type = cover::picture_type(values.next().unwrap())
+                    .unwrap_or_else(|e| exit_with(ErrorKind::InvalidValue, e));
+                actions.push(ModifyAction::Picture {
+                    picture_type,
+                    description: picture_desc.clone(),
+                    path: PathBuf::from(values.next().unwrap()),
+                });
+            }
+        }
         if let Some(v) = matches.get_one::<String>("delete-tag") {
             actions.push(ModifyAction::DeleteTag(v.clone()));
         }
         if let Some(mut values) = matches.get_many::<String>("custom-flag") {
             let frame_id = values.next().unwrap();
             let frame_content = values.next().unwrap();
-            actions.push(ModifyAction::Custom(
-                frame_id.clone(),
-                frame_content.clone(),
-            ));
+            let frame = custom::build_frame(frame_id, frame_content)
+                .unwrap_or_else(|e| exit_with(ErrorKind::InvalidValue, e));
+            actions.push(ModifyAction::Custom(frame));
+        }
+
+        let comment_desc = matches
+            .get_one::<String>("comment-desc")
+            .cloned()
+            .unwrap_or_default();
+        let comment_lang = matches.get_one::<String>("comment-lang").cloned();
+        if let Some(v) = matches.get_one::<String>("comment") {
+            actions.push(ModifyAction::Comment {
+                lang: comment_lang.clone().unwrap_or_else(|| "eng".to_string()),
+                description: comment_desc.clone(),
+                text: v.clone(),
+            });
+        }
+        if matches.get_flag("remove-comment") {
+            actions.push(ModifyAction::RemoveComment {
+                lang: comment_lang,
+                description: comment_desc,
+            });
+        }
+
+        if let Some(occurrences) = matches.get_occurrences::<String>("txxx") {
+            for mut values in occurrences {
+                let description = values.next().unwrap();
+                let value = values.next().unwrap();
+                actions.push(ModifyAction::UserText(description.clone(), value.clone()));
+            }
+        }
+        if let Some(occurrences) = matches.get_occurrences::<String>("wxxx") {
+            for mut values in occurrences {
+                let description = values.next().unwrap();
+                let link = values.next().unwrap();
+                actions.push(ModifyAction::UserLink(description.clone(), link.clone()));
+            }
+        }
+        for (arg, id) in [("remove-txxx", "TXXX"), ("remove-wxxx", "WXXX")] {
+            if let Some(values) = matches.get_many::<String>(arg) {
+                for description in values {
+                    actions.push(ModifyAction::RemoveUserFrame(
+                        id.to_string(),
+                        description.clone(),
+                    ));
+                }
+            }
+        }
+
+        let lyrics_lang = matches
+            .get_one::<String>("lyrics-lang")
+            .cloned()
+            .unwrap_or_else(|| "eng".to_string());
+        let lyrics_desc = matches
+            .get_one::<String>("lyrics-desc")
+            .cloned()
+            .unwrap_or_default();
+        if let Some(v) = matches.get_one::<String>("lyrics") {
+            let text = lyrics::read_source(v).unwrap_or_else(|e| exit_with(ErrorKind::Io, e));
+            actions.push(ModifyAction::Lyrics {
+                lang: lyrics_lang.clone(),
+                description: lyrics_desc.clone(),
+                text,
+            });
+        }
+        if let Some(v) = matches.get_one::<String>("lrc") {
+            let content = lyrics::read_source(v)
+                .and_then(|text| lyrics::parse_lrc(&text))
+                .unwrap_or_else(|e| exit_with(ErrorKind::Io, e));
+            actions.push(ModifyAction::SyncedLyrics {
+                lang: lyrics_lang.clone(),
+                description: lyrics_desc.clone(),
+                content,
+            });
+        }
+        if matches.get_flag("auto-lrc") {
+            actions.push(ModifyAction::AutoLrc {
+                lang: lyrics_lang,
+                description: lyrics_desc,
+            });
+        }
+        if let Some(v) = matches.get_many::<String>("split") {
+            actions.push(ModifyAction::SplitValues(v.cloned().collect()));
         }
 
         let changeset = ChangeSet {
             actions,
+            from_filename: matches.get_one::<FilenamePattern>("from-filename").cloned(),
+            organize: matches.get_one::<String>("organize").map(|dest| Organize {
+                dest: PathBuf::from(dest),
+                layout: matches
+                    .get_one::<Template>("layout")
+                    .cloned()
+                    .unwrap_or_else(|| Template::parse(organize::DEFAULT_LAYOUT).unwrap()),
+                copy: matches.get_flag("copy"),
+            }),
+            auto_number: matches.get_flag("auto-number"),
+            id3v1: if matches.get_flag("v1") {
+                Some(Id3v1Mode::Write)
+            } else if matches.get_flag("sync-v1") {
+                Some(Id3v1Mode::Sync)
+            } else if matches.get_flag("strip-v1") {
+                Some(Id3v1Mode::Strip)
+            } else {
+                None
+            },
             delete_all: matches.get_flag("delete-all"),
-            format_file: matches.get_flag("format-file"),
+            convert: matches.get_flag("convert"),
+            cover: CoverOptions {
+                raw: matches.get_flag("cover-raw"),
+                format: *matches.get_one::<CoverFormat>("cover-format").unwrap(),
+                quality: *matches.get_one::<u8>("cover-quality").unwrap(),
+                max_size: matches.get_one::<u32>("cover-max-size").copied(),
+                budget: matches
+                    .get_one::<u32>("cover-budget")
+                    .map(|kb| *kb as usize * 1024),
+            },
+            separator: matches.get_one::<String>("separator").unwrap().clone(),
+            format_file: matches.get_flag("format-file").then(|| {
+                matches
+                    .get_one::<Template>("template")
+                    .cloned()
+                    .unwrap_or_default()
+            }),
             print_details: matches.get_flag("print"),
+            list_user_frames: matches.get_flag("list-user-frames"),
+            list_pictures: matches.get_flag("list-pictures"),
+            export_lyrics: matches.get_flag("export-lyrics"),
+            export_lrc: matches.get_flag("export-lrc"),
+            overwrite: matches.get_flag("overwrite"),
+            extract_art: matches.get_one::<ExtractArt>("extract-art").copied(),
+            dry_run: matches.get_flag("dry-run"),
+            output: *matches.get_one::<OutputFormat>("output").unwrap(),
+            include_pictures: matches.get_flag("include-pictures"),
         };
 
         let path_str = PathBuf::from(
@@ -181,16 +864,80 @@ impl MyParser {
         let config = AppConfig {
             target_path: path_str,
             recursive: matches.get_flag("recursive"),
+            import: matches.get_one::<String>("import").map(PathBuf::from),
+            export_csv: matches.get_one::<String>("export-csv").map(PathBuf::from),
+            import_csv: matches.get_one::<String>("import-csv").map(PathBuf::from),
+            csv_fields: match matches.get_many::<String>("csv-fields") {
+                Some(fields) => std::iter::once("path".to_string())
+                    .chain(fields.filter(|f| *f != "path").cloned())
+                    .collect(),
+                None => table::DEFAULT_FIELDS.map(String::from).to_vec(),
+            },
+            max_depth: matches.get_one::<usize>("max-depth").copied(),
+            follow_symlinks: matches.get_flag("follow-symlinks"),
+            include_hidden: matches.get_flag("hidden"),
+            include: matches
+                .get_many::<Pattern>("include")
+                .map(|v| v.cloned().collect())
+                .unwrap_or_default(),
+            exclude: matches
+                .get_many::<Pattern>("exclude")
+                .map(|v| v.cloned().collect())
+                .unwrap_or_default(),
             list_genres: matches.get_flag("list-genres"),
             version: if matches.get_flag("v22") {
-                id3::Version::Id3v22
+                VersionPolicy::Force(id3::Version::Id3v22)
             } else if matches.get_flag("v23") {
-                id3::Version::Id3v23
+                VersionPolicy::Force(id3::Version::Id3v23)
+            } else if matches.get_flag("v24") {
+                VersionPolicy::Force(id3::Version::Id3v24)
             } else {
-                id3::Version::Id3v24
+                VersionPolicy::Keep
             },
+            backup: if let Some(dir) = matches.get_one::<String>("backup-dir") {
+                Backup::Dir(PathBuf::from(dir))
+            } else if matches.get_flag("backup") {
+                Backup::Sibling
+            } else {
+                Backup::None
+            },
+            journal: if matches.get_flag("no-journal") {
+                JournalMode::Off
+            } else if let Some(file) = matches.get_one::<String>("journal") {
+                JournalMode::File(PathBuf::from(file))
+            } else {
+                JournalMode::Auto
+            },
+            undo: matches
+                .get_one::<String>("undo")
+                .map(|v| (!v.is_empty()).then(|| PathBuf::from(v))),
         };
 
-        return (changeset, config);
+        (changeset, config)
+    }
+}
+
+/// Reports an error found while turning the arguments into actions and exits like clap does.
+fn exit_with(kind: ErrorKind, message: String) -> ! {
+    clap::Error::raw(kind, format!("{}\n", message)).exit()
+}
+
+fn parse_lang(s: &str) -> Result<String, String> {
+    if s.len() == 3 && s.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(s.to_ascii_lowercase())
+    } else {
+        Err("Language must be a three letter ISO-639-2 code, e.g. 'eng'".to_string())
+    }
+}
+
+fn parse_pattern(s: &str) -> Result<Pattern, String> {
+    Pattern::new(s).map_err(|e| format!("Invalid glob pattern :: {}", e))
+}
+
+fn parse_csv_field(s: &str) -> Result<String, String> {
+    let field = s.trim();
+    if field != "path" {
+        table::check_field(field)?;
     }
+    Ok(field.to_string())
 }
@@ -0,0 +1,163 @@
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    models::{
+        controls::Backup,
+        id3v1::{self, Trailer},
+    },
+    status,
+};
+
+/// Writes the tag to a copy of the file in the same directory, syncs it and renames it over the
+/// original, so a crash or a failed encode never leaves a half written or tagless file behind.
+pub fn write_atomic(
+    path: &Path,
+    tag: &id3::Tag,
+    version: id3::Version,
+    trailer: &Trailer,
+) -> Result<(), String> {
+    replace_atomic(path, |file| {
+        tag.write_to_file(&mut *file, version)
+            .map_err(|e| format!("Error writing tag :: {}", e))?;
+        id3v1::write(file, trailer)
+    })
+}
+
+/// Replaces or removes the ID3v1 tag only, the same way as [`write_atomic`].
+pub fn write_trailer(path: &Path, trailer: &Trailer) -> Result<(), String> {
+    replace_atomic(path, |file| id3v1::write(file, trailer))
+}
+
+fn replace_atomic(
+    path: &Path,
+    edit: impl FnOnce(&mut fs::File) -> Result<(), String>,
+) -> Result<(), String> {
+    // Renaming over a symlink would replace the link and leave the file it points to untouched
+    let path = &fs::canonicalize(path).map_err(|e| format!("Error resolving path :: {}", e))?;
+    let tmp = sibling_with_suffix(path, ".editag-tmp", true);
+
+    let result = (|| {
+        fs::copy(path, &tmp).map_err(|e| format!("Error creating temporary file :: {}", e))?;
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&tmp)
+            .map_err(|e| format!("Error opening temporary file :: {}", e))?;
+        edit(&mut file)?;
+        file.sync_all()
+            .map_err(|e| format!("Error syncing temporary file :: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Error replacing file :: {}", e))
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return result;
+    }
+
+    sync_parent(path);
+    Ok(())
+}
+
+/// Copies the file aside before it is modified, according to the backup mode. An existing
+/// backup is kept, as it holds the file from before the first change.
+pub fn backup(path: &Path, mode: &Backup) -> Result<(), String> {
+    let target = match mode {
+        Backup::None => return Ok(()),
+        Backup::Sibling => sibling_with_suffix(path, ".bak", false),
+        Backup::Dir(dir) => dir.join(mirrored(
+            &fs::canonicalize(path).map_err(|e| format!("Error resolving path :: {}", e))?,
+        )),
+    };
+    if target.exists() {
+        status!("Keeping the earlier backup {:?}", target);
+        return Ok(());
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Error creating backup directory :: {}", e))?;
+    }
+    fs::copy(path, &target).map_err(|e| format!("Error writing backup {:?} :: {}", target, e))?;
+    status!("Backed up to {:?}", target);
+    Ok(())
+}
+
+/// Moves a file, creating the target's directories. Never replaces an existing file, and falls
+/// back to copying when the target is on another filesystem.
+pub fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    prepare_target(to)?;
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_synced(from, to)?;
+            fs::remove_file(from).map_err(|e| format!("Error removing {:?} :: {}", from, e))
+        }
+        result => result.map_err(|e| format!("Error moving {:?} to {:?} :: {}", from, to, e)),
+    }
+}
+
+/// Copies a file, creating the target's directories. Never replaces an existing file.
+pub fn copy_file(from: &Path, to: &Path) -> Result<(), String> {
+    prepare_target(to)?;
+    copy_synced(from, to)
+}
+
+fn prepare_target(to: &Path) -> Result<(), String> {
+    if to.exists() {
+        return Err(format!("{:?} already exists", to));
+    }
+    if let Some(parent) = to.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Error creating directory {:?} :: {}", parent, e))?;
+    }
+    Ok(())
+}
+
+fn copy_synced(from: &Path, to: &Path) -> Result<(), String> {
+    let result = fs::copy(from, to)
+        .and_then(|_| fs::File::open(to))
+        .and_then(|f| f.sync_all());
+    if let Err(e) = result {
+        let _ = fs::remove_file(to);
+        return Err(format!("Error copying {:?} to {:?} :: {}", from, to, e));
+    }
+    sync_parent(to);
+    Ok(())
+}
+
+/// `dir/name` -> `dir/name<suffix>`, or `dir/.name<suffix>` when hidden.
+fn sibling_with_suffix(path: &Path, suffix: &str, hidden: bool) -> PathBuf {
+    let mut name = OsString::new();
+    if hidden {
+        name.push(".");
+    }
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// The canonical path of the track without its root, so every file keeps a distinct location
+/// inside the backup directory.
+fn mirrored(path: &Path) -> PathBuf {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Makes the rename durable. Directories can't be opened for syncing everywhere, so failures
+/// are ignored.
+fn sync_parent(path: &Path) {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = fs::File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
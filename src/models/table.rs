@@ -0,0 +1,243 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::{
+    models::{controls::ModifyAction, dates, manifest, track::Track},
+    status,
+};
+
+pub const DEFAULT_FIELDS: [&str; 9] = [
+    "path",
+    "title",
+    "artist",
+    "album",
+    "album_artist",
+    "year",
+    "track",
+    "genre",
+    "comment",
+];
+
+/// Fields that can be added with `--csv-fields` besides the defaults.
+const EXTRA_FIELDS: [&str; 6] = [
+    "total_tracks",
+    "disc",
+    "total_discs",
+    "date",
+    "original_date",
+    "release_date",
+];
+
+pub struct Row {
+    pub path: PathBuf,
+    pub cells: Vec<(String, String)>,
+}
+
+/// Checks a column name is something [`Track::field`] can read and [`changed_actions`] can
+/// write back.
+pub fn check_field(name: &str) -> Result<(), String> {
+    let known = DEFAULT_FIELDS.contains(&name)
+        || EXTRA_FIELDS.contains(&name)
+        || name.starts_with("txxx:")
+        || name.starts_with("wxxx:")
+        || (name.len() == 4
+            && (name.starts_with('T') || name.starts_with('W'))
+            && name != "TXXX"
+            && name != "WXXX"
+            && name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()));
+    if known {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown CSV column {:?}, expected a field name, txxx:<desc>, wxxx:<desc> or a text or link frame ID",
+            name
+        ))
+    }
+}
+
+/// Writes one row per file with one column per field. Paths are written relative to the CSV
+/// when the file lies below it, so the sheet keeps working if the library is moved.
+pub fn export(csv_path: &Path, fields: &[String], files: &[PathBuf]) -> Result<(), String> {
+    let base = base_dir(csv_path);
+    let mut writer = csv::Writer::from_path(csv_path)
+        .map_err(|e| format!("Error creating CSV {:?} :: {}", csv_path, e))?;
+    writer
+        .write_record(fields)
+        .map_err(|e| format!("Error writing CSV :: {}", e))?;
+
+    let mut exported = 0;
+    for file in files {
+        let track = match Track::load(file.clone()) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("Error loading file {:?}: {}", file, e);
+                continue;
+            }
+        };
+        let row = fields.iter().map(|field| match field.as_str() {
+            "path" => relative_path(&track.path, &base),
+            _ => track.field(field).unwrap_or_default(),
+        });
+        writer
+            .write_record(row)
+            .map_err(|e| format!("Error writing CSV :: {}", e))?;
+        exported += 1;
+    }
+
+    writer
+        .flush()
+        .map_err(|e| format!("Error writing CSV :: {}", e))?;
+    status!("Exported {} files to {:?}", exported, csv_path);
+    Ok(())
+}
+
+/// Reads an edited CSV back. Relative paths are resolved against the CSV's directory.
+pub fn load(csv_path: &Path) -> Result<Vec<Row>, String> {
+    let mut reader = csv::Reader::from_path(csv_path)
+        .map_err(|e| format!("Error reading CSV {:?} :: {}", csv_path, e))?;
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Error reading CSV :: {}", e))?
+        .clone();
+
+    let path_column = headers
+        .iter()
+        .position(|h| h == "path")
+        .ok_or("The CSV must have a 'path' column")?;
+    for header in headers.iter().filter(|h| *h != "path") {
+        check_field(header)?;
+    }
+
+    let base_dir = csv_path.parent().unwrap_or(Path::new(""));
+    let mut rows = Vec::new();
+    for (line, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("Error reading CSV :: {}", e))?;
+        let path = record
+            .get(path_column)
+            .filter(|p| !p.is_empty())
+            .ok_or_else(|| format!("Row {} has no path", line + 2))?;
+        let cells = headers
+            .iter()
+            .zip(record.iter())
+            .filter(|(header, _)| *header != "path")
+            .map(|(header, cell)| (header.to_string(), cell.to_string()))
+            .collect();
+        rows.push(Row {
+            path: base_dir.join(path),
+            cells,
+        });
+    }
+    Ok(rows)
+}
+
+/// Actions for the cells that differ from the track. An emptied cell removes the field.
+pub fn changed_actions(
+    track: &Track,
+    cells: &[(String, String)],
+) -> Result<Vec<ModifyAction>, String> {
+    let base_dir = track.path.parent().unwrap_or(Path::new(""));
+    let mut actions = Vec::new();
+    for (field, cell) in cells {
+        let current = track.field(field).unwrap_or_default();
+        if *cell == current {
+            continue;
+        }
+        if field == "comment" {
+            // Replaces whichever language the existing comment was written in
+            actions.push(ModifyAction::RemoveComment {
+                lang: None,
+                description: String::new(),
+            });
+        }
+        if cell.is_empty() {
+            actions.extend(clear_actions(field));
+        } else if let Some(date) = changed_year(track, field, cell) {
+            actions.push(ModifyAction::RecordingDate(date));
+        } else {
+            actions.extend(
+                manifest::field_actions(field, &Value::String(cell.clone()), base_dir)
+                    .map_err(|e| format!("Error in {:?} :: {}", track.path, e))?,
+            );
+        }
+    }
+    Ok(actions)
+}
+
+/// The recording date with the year of an edited `year` cell, keeping the month and day of a full
+/// date instead of replacing it with the year alone.
+fn changed_year(track: &Track, field: &str, cell: &str) -> Option<id3::Timestamp> {
+    if field != "year" {
+        return None;
+    }
+    let current = dates::recorded(&track.tag)?;
+    Some(id3::Timestamp {
+        year: cell.trim().parse().ok()?,
+        ..current
+    })
+}
+
+fn clear_actions(field: &str) -> Vec<ModifyAction> {
+    let ids: &[&str] = match field {
+        "title" => &["TIT2"],
+        "artist" => &["TPE1"],
+        "album" => &["TALB"],
+        "album_artist" => &["TPE2"],
+        "genre" => &["TCON"],
+        "year" | "date" => &["TDRC", "TYER", "TDAT", "TIME"],
+        "original_date" => &["TDOR", "TORY"],
+        "release_date" => &["TDRL"],
+        "track" => &["TRCK"],
+        "disc" => &["TPOS"],
+        "total_tracks" => return vec![ModifyAction::TotalTracks(None)],
+        "total_discs" => return vec![ModifyAction::TotalDiscs(None)],
+        "comment" => &[],
+        _ => {
+            if let Some(description) = field.strip_prefix("txxx:") {
+                return vec![ModifyAction::RemoveUserFrame(
+                    "TXXX".to_string(),
+                    description.to_string(),
+                )];
+            }
+            if let Some(description) = field.strip_prefix("wxxx:") {
+                return vec![ModifyAction::RemoveUserFrame(
+                    "WXXX".to_string(),
+                    description.to_string(),
+                )];
+            }
+            return vec![ModifyAction::DeleteTag(field.to_string())];
+        }
+    };
+    ids.iter()
+        .map(|id| ModifyAction::DeleteTag(id.to_string()))
+        .collect()
+}
+
+fn base_dir(csv_path: &Path) -> PathBuf {
+    let parent = match csv_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    resolved(parent)
+}
+
+fn relative_path(path: &Path, base: &Path) -> String {
+    let absolute = resolved(path);
+    absolute
+        .strip_prefix(base)
+        .unwrap_or(&absolute)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Canonical so `..` in either path doesn't defeat the prefix check.
+fn resolved(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,201 @@
+use crate::models::{table, track::Track};
+
+pub const DEFAULT_TEMPLATE: &str = "{track:02} - {title}";
+
+/// A file name template such as `{track:02} - [{disc}-]{album_artist|artist} - {title}`.
+///
+/// - `{field}` inserts a field by its manifest name, `albumartist` is accepted for `album_artist`
+/// - `{field:02}` zero pads a number to the given width
+/// - `{a|b|"text"}` falls back to the next field, or literal text, when one is missing
+/// - `[...]` is left out when a field inside it is missing
+///
+/// `{{`, `}}`, `[[` and `]]` write the bracket itself.
+#[derive(Clone, Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Literal(String),
+    Field {
+        choices: Vec<Choice>,
+        width: Option<usize>,
+    },
+    Optional(Vec<Part>),
+}
+
+#[derive(Clone, Debug)]
+enum Choice {
+    Field(String),
+    Text(String),
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut chars = source.chars().peekable();
+        let parts = parse_parts(&mut chars, false)
+            .map_err(|e| format!("Invalid template {:?} :: {}", source, e))?;
+        Ok(Template { parts })
+    }
+
+    /// Renders the template with every field value made safe to use in a file name.
+    pub fn render(&self, track: &Track) -> Result<String, String> {
+        render_parts(&self.parts, track).map_err(|field| format!("{} is missing", field))
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::parse(DEFAULT_TEMPLATE).unwrap()
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn parse_parts(chars: &mut Chars, in_optional: bool) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' | '[' | ']' if chars.peek() == Some(&c) => {
+                chars.next();
+                literal.push(c);
+            }
+            '{' => {
+                flush(&mut parts, &mut literal);
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => inner.push(c),
+                        None => return Err("unclosed '{'".to_string()),
+                    }
+                }
+                parts.push(parse_field(&inner)?);
+            }
+            '[' => {
+                flush(&mut parts, &mut literal);
+                parts.push(Part::Optional(parse_parts(chars, true)?));
+            }
+            ']' if in_optional => {
+                flush(&mut parts, &mut literal);
+                return Ok(parts);
+            }
+            '}' | ']' => return Err(format!("unmatched '{}'", c)),
+            c => literal.push(c),
+        }
+    }
+
+    if in_optional {
+        return Err("unclosed '['".to_string());
+    }
+    flush(&mut parts, &mut literal);
+    Ok(parts)
+}
+
+fn flush(parts: &mut Vec<Part>, literal: &mut String) {
+    if !literal.is_empty() {
+        parts.push(Part::Literal(std::mem::take(literal)));
+    }
+}
+
+fn parse_field(inner: &str) -> Result<Part, String> {
+    // The width comes after the last ':' so user frame fields keep their 'txxx:' prefix
+    let (names, width) = match inner.rsplit_once(':') {
+        Some((names, spec)) if spec.starts_with('0') && !spec.contains('"') => {
+            let width = spec[1..]
+                .parse()
+                .map_err(|_| format!("invalid width {:?}, expected e.g. ':02'", spec))?;
+            (names, Some(width))
+        }
+        _ => (inner, None),
+    };
+
+    let choices = names
+        .split('|')
+        .map(|name| {
+            let name = name.trim();
+            if let Some(text) = name
+                .strip_prefix('"')
+                .and_then(|rest| rest.strip_suffix('"'))
+            {
+                return Ok(Choice::Text(text.to_string()));
+            }
+            let name = if name == "albumartist" {
+                "album_artist"
+            } else {
+                name
+            };
+            if name != "path" && table::check_field(name).is_ok() {
+                Ok(Choice::Field(name.to_string()))
+            } else {
+                Err(format!("unknown field {{{}}}", name))
+            }
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(Part::Field { choices, width })
+}
+
+/// Fails with the first required field that is missing.
+fn render_parts(parts: &[Part], track: &Track) -> Result<String, String> {
+    let mut out = String::new();
+    for part in parts {
+        match part {
+            Part::Literal(text) => out.push_str(text),
+            Part::Optional(inner) => {
+                if let Ok(text) = render_parts(inner, track) {
+                    out.push_str(&text);
+                }
+            }
+            Part::Field { choices, width } => {
+                let value = choices.iter().find_map(|choice| match choice {
+                    Choice::Text(text) => Some(text.clone()),
+                    Choice::Field(name) => track.field(name).filter(|v| !v.trim().is_empty()),
+                });
+                let Some(value) = value else {
+                    let names: Vec<_> = choices
+                        .iter()
+                        .filter_map(|choice| match choice {
+                            Choice::Field(name) => Some(name.as_str()),
+                            Choice::Text(_) => None,
+                        })
+                        .collect();
+                    return Err(format!("{{{}}}", names.join("|")));
+                };
+                let value = match width {
+                    Some(width) if value.chars().all(|c| c.is_ascii_digit()) => {
+                        format!("{:0>width$}", value, width = width)
+                    }
+                    _ => value,
+                };
+                out.push_str(&sanitize(&value));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Replaces path separators and characters most filesystems reject, and drops control
+/// characters, so a value can't create directories or make the rename fail.
+pub fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Drops the trailing dots and spaces some filesystems discard, and a leading dot which would
+/// hide the file.
+pub fn clean_name(name: &str) -> String {
+    let name = name.trim().trim_end_matches('.');
+    match name.strip_prefix('.') {
+        Some(rest) => format!("_{}", rest),
+        None => name.to_string(),
+    }
+}
@@ -1,28 +1,67 @@
-use std::{fs, path::PathBuf};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use id3::{TagLike, Timestamp};
-use image::ImageReader;
-use std::io::Cursor;
+use id3::{
+    frame::{
+        Content, ExtendedLink, ExtendedText, Picture, PictureType, SynchronisedLyrics,
+        SynchronisedLyricsType, TimestampFormat,
+    },
+    Frame, TagLike,
+};
 
-use crate::models::controls::{ChangeSet, ModifyAction};
+use crate::{
+    models::{
+        artwork::{self, ExtractArt},
+        controls::{Backup, ChangeSet, Id3v1Mode, ModifyAction},
+        convert,
+        cover::{self, CoverOptions, PictureSelector},
+        dates, frames, genre,
+        id3v1::{self, Trailer},
+        lyrics,
+        output::{self, OutputFormat},
+        storage,
+        template::{self, Template},
+        values,
+    },
+    status,
+};
 
 #[derive(Debug)]
 pub struct Track {
     pub tag: id3::Tag,
     pub path: PathBuf,
+    /// Version of the tag read from the file, `None` if the file had no tag.
+    pub original_version: Option<id3::Version>,
+    /// The raw ID3v1 tag at the end of the file, if any.
+    pub id3v1: Option<Vec<u8>>,
+    /// What saving does to the ID3v1 tag, decided by [`Track::apply`].
+    pub trailer: Trailer,
 }
 
 impl Track {
     pub fn load(path: PathBuf) -> Result<Self, String> {
-        let tag = match id3::Tag::read_from_path(&path) {
-            Ok(t) => t,
+        let id3v1 = id3v1::read_raw(&path);
+        let (tag, original_version) = match frames::read_from_path(&path) {
+            Ok(t) => {
+                let version = t.version();
+                (t, Some(version))
+            }
             Err(id3::Error {
                 kind: id3::ErrorKind::NoTag,
                 ..
-            }) => {
-                println!("No tag found for {:?}, creating a new one", path);
-                id3::Tag::new()
-            }
+            }) => match id3::v1::Tag::read_from_path(&path) {
+                Ok(v1) if id3v1.is_some() => {
+                    status!("No ID3v2 tag found for {:?}, using its ID3v1 tag", path);
+                    (id3::Tag::from(v1), None)
+                }
+                _ => {
+                    status!("No tag found for {:?}, creating a new one", path);
+                    (id3::Tag::new(), None)
+                }
+            },
             Err(e) => {
                 return Err(format!(
                     "Error occurred when opening ID3 tag :: {}\nWas the correct file path used?",
@@ -30,79 +69,411 @@ impl Track {
                 ))
             }
         };
-        Ok(Track { path, tag })
+        Ok(Track {
+            path,
+            tag,
+            original_version,
+            id3v1,
+            trailer: Trailer::Keep,
+        })
     }
 
-    fn set_cover_art(&mut self, img_path: &PathBuf) -> Result<(), String> {
-        let img = ImageReader::open(img_path)
-            .map_err(|e| format!("Error opening image :: {}", e))?
-            .with_guessed_format()
-            .map_err(|e| format!("Error guessing format :: {}", e))?
-            .decode()
-            .map_err(|e| format!("Error decoding image :: {}", e))?;
+    /// Embeds the image as the picture of the given type, replacing the ones already there.
+    fn set_picture(
+        &mut self,
+        picture_type: PictureType,
+        description: &str,
+        img_path: &Path,
+        options: &CoverOptions,
+    ) -> Result<(), String> {
+        let (mime_type, data) = cover::prepare(img_path, options)?;
 
-        let mut raw_image = Cursor::new(Vec::new());
+        self.remove_frames_where("APIC", |f| {
+            f.content()
+                .picture()
+                .is_some_and(|p| p.picture_type == picture_type)
+        });
+        self.tag.add_frame(Picture {
+            mime_type,
+            picture_type,
+            description: description.to_string(),
+            data,
+        });
 
-        img.write_to(&mut raw_image, image::ImageFormat::Jpeg)
-            .map_err(|e| format!("Error processing image :: {}", e))?;
+        Ok(())
+    }
 
-        self.tag.remove_all_pictures();
-        self.tag.add_frame(id3::frame::Picture {
-            mime_type: "image/jpeg".to_string(),
-            picture_type: id3::frame::PictureType::CoverFront,
-            description: "Cover Art".to_string(),
-            data: raw_image.into_inner(),
-        });
+    /// Removes the pictures matching any of the selectors, numbered as `--list-pictures` shows
+    /// them before anything is removed.
+    fn remove_pictures(&mut self, selectors: &[PictureSelector]) -> Vec<Picture> {
+        let count = self.tag.pictures().count();
+        for selector in selectors {
+            if let PictureSelector::Index(n) = selector {
+                if *n > count {
+                    status!("No picture number {} found", n);
+                }
+            }
+        }
+        let mut number = 0;
+        self.remove_frames_where("APIC", |f| {
+            number += 1;
+            f.content().picture().is_some_and(|p| {
+                selectors.iter().any(|s| match s {
+                    PictureSelector::Type(t) => p.picture_type == *t,
+                    PictureSelector::Index(n) => number == *n,
+                })
+            })
+        })
+        .into_iter()
+        .filter_map(|f| f.content().picture().cloned())
+        .collect()
+    }
+
+    /// The values of a text frame, several for ID3v2.4 multi-value frames.
+    fn text_values(&self, id: &str) -> Vec<String> {
+        self.tag
+            .get(id)
+            .and_then(|f| f.content().text_values())
+            .map(|values| values.map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    /// Removes every frame with the given ID that matches `pred`, in order, keeping the rest where
+    /// they were. The kept frames are never added again, as `add_frame` would drop the ones id3
+    /// considers duplicates, such as two pictures of the same type.
+    fn remove_frames_where(
+        &mut self,
+        id: &str,
+        mut pred: impl FnMut(&Frame) -> bool,
+    ) -> Vec<Frame> {
+        let (removed, kept): (Vec<Frame>, Vec<Frame>) = self
+            .tag
+            .frames()
+            .cloned()
+            .partition(|f| f.id() == id && pred(f));
+        if !removed.is_empty() {
+            let mut tag = id3::Tag::with_version(self.tag.version());
+            tag.extend(kept);
+            self.tag = tag;
+        }
+        removed
+    }
+
+    pub fn export_lyrics(&self, dry_run: bool, overwrite: bool) -> Result<(), String> {
+        let all: Vec<_> = self.tag.lyrics().collect();
+        if all.is_empty() {
+            status!("No lyrics found in {:?}", self.path);
+        }
+        let mut taken = HashSet::new();
+        for l in &all {
+            let out = lyrics::sidecar_path(
+                &self.path,
+                &l.lang,
+                &l.description,
+                all.len() == 1,
+                "txt",
+                &mut taken,
+            );
+            if out.exists() && !overwrite {
+                status!("{:?} already exists, use --overwrite to replace it", out);
+                continue;
+            }
+            if dry_run {
+                status!("Would export lyrics to {:?}", out);
+                continue;
+            }
+            fs::write(&out, &l.text).map_err(|e| format!("Error writing lyrics :: {}", e))?;
+            status!("Exported lyrics to {:?}", out);
+        }
+        Ok(())
+    }
 
+    /// Writes the embedded pictures next to the track. In `album` mode the front cover was
+    /// already extracted for the whole directory, so only the other pictures are written.
+    pub fn export_art(&self, mode: ExtractArt, dry_run: bool) -> Result<(), String> {
+        let pictures: Vec<_> = self
+            .tag
+            .pictures()
+            .filter(|p| mode == ExtractArt::Tracks || p.picture_type != PictureType::CoverFront)
+            .collect();
+        if pictures.is_empty() && mode == ExtractArt::Tracks {
+            status!("No pictures found in {:?}", self.path);
+        }
+        let stem = self
+            .path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let mut written = HashSet::new();
+        for p in pictures {
+            let base = format!("{}.{}", stem, cover::type_name(p.picture_type));
+            let out = lyrics::numbered(
+                &self.path,
+                &base,
+                artwork::extension(&p.mime_type),
+                &mut written,
+            );
+            if dry_run {
+                status!("Would extract {} to {:?}", p.picture_type, out);
+                continue;
+            }
+            fs::write(&out, &p.data).map_err(|e| format!("Error writing picture :: {}", e))?;
+            status!("Extracted {} to {:?}", p.picture_type, out);
+        }
         Ok(())
     }
 
-    pub fn format_filename(&mut self) -> Result<(), String> {
-        if let (Some(n), Some(t)) = (self.tag.track(), self.tag.title()) {
-            let parent = self
-                .path
-                .parent()
-                .unwrap_or_else(|| std::path::Path::new(""));
+    pub fn export_lrc(&self, dry_run: bool, overwrite: bool) -> Result<(), String> {
+        let all: Vec<_> = self.tag.synchronised_lyrics().collect();
+        if all.is_empty() {
+            status!("No synchronised lyrics found in {:?}", self.path);
+        }
+        let mut taken = HashSet::new();
+        for l in &all {
+            if l.timestamp_format != TimestampFormat::Ms {
+                status!(
+                    "Skipping synchronised lyrics [{}] {:?}: MPEG frame timestamps can't be written as LRC",
+                    l.lang, l.description
+                );
+                continue;
+            }
+            let out = lyrics::sidecar_path(
+                &self.path,
+                &l.lang,
+                &l.description,
+                all.len() == 1,
+                "lrc",
+                &mut taken,
+            );
+            if out.exists() && !overwrite {
+                status!("{:?} already exists, use --overwrite to replace it", out);
+                continue;
+            }
+            if dry_run {
+                status!("Would export synchronised lyrics to {:?}", out);
+                continue;
+            }
+            fs::write(&out, lyrics::format_lrc(&l.content))
+                .map_err(|e| format!("Error writing LRC :: {}", e))?;
+            status!("Exported synchronised lyrics to {:?}", out);
+        }
+        Ok(())
+    }
+
+    /// Replaces the synchronised lyrics with the same language and description. id3 would only
+    /// tell them apart by language and content type, replacing the lyrics of other descriptions.
+    fn set_synced_lyrics(&mut self, lang: &str, description: &str, content: Vec<(u32, String)>) {
+        let lines = content.len();
+        self.remove_frames_where("SYLT", |f| {
+            matches!(f.content(), Content::SynchronisedLyrics(l) if l.lang == lang && l.description == description)
+        });
+        self.tag.extend([Frame::from(SynchronisedLyrics {
+            lang: lang.to_string(),
+            timestamp_format: TimestampFormat::Ms,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: description.to_string(),
+            content,
+        })]);
+        status!(
+            "Set synchronised lyrics [{}] {:?} successfully: {} lines",
+            lang,
+            description,
+            lines
+        );
+    }
 
-            let ext = self
-                .path
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("mp3");
+    /// The path the file would be renamed to, keeping its directory and extension.
+    pub fn formatted_path(&self, template: &Template) -> Result<PathBuf, String> {
+        let name = template::clean_name(&template.render(self)?);
+        if name.is_empty() {
+            return Err("the template produced an empty file name".to_string());
+        }
+        if name.contains('/') {
+            return Err(format!("{:?} names a directory, not a file", name));
+        }
+
+        let parent = self
+            .path
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new(""));
+        let ext = self
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("mp3");
+        Ok(parent.join(format!("{}.{}", name, ext)))
+    }
 
-            let new_name = format!("{:0>2} - {}.{}", n, t, ext);
-            let new_path = parent.join(new_name);
+    /// Reads a field by the names used in manifests and CSV columns: the common fields,
+    /// `txxx:<desc>`, `wxxx:<desc>` or the ID of a text or link frame.
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name {
+            // ID3v2.3 values hold a null where the text had a `/`
+            "title" => self.tag.title().map(|t| t.replace('\0', "/")),
+            "artist" if self.tag.version() != id3::Version::Id3v24 => {
+                self.tag.artist().map(|a| a.replace('\0', "/"))
+            }
+            "artist" => self.tag.artists().map(|a| a.join(values::SEPARATOR)),
+            "album" => self.tag.album().map(|a| a.replace('\0', "/")),
+            "album_artist" => self.tag.album_artist().map(|a| a.replace('\0', "/")),
+            "genre" => genre::normalized(&self.tag).map(|g| g.join(values::SEPARATOR)),
+            "year" => dates::recorded(&self.tag).map(|t| t.year.to_string()),
+            "date" => dates::recorded(&self.tag).map(|t| t.to_string()),
+            "original_date" => dates::original(&self.tag).map(|t| t.to_string()),
+            "release_date" => self.tag.date_released().map(|t| t.to_string()),
+            "track" => self.tag.track().map(|n| n.to_string()),
+            "total_tracks" => self.tag.total_tracks().map(|n| n.to_string()),
+            "disc" => self.tag.disc().map(|n| n.to_string()),
+            "total_discs" => self.tag.total_discs().map(|n| n.to_string()),
+            "comment" => self
+                .tag
+                .comments()
+                .filter(|c| c.description.is_empty())
+                .min_by_key(|c| c.lang != "eng")
+                .map(|c| c.text.clone()),
+            _ => {
+                if let Some(description) = name.strip_prefix("txxx:") {
+                    return self
+                        .tag
+                        .extended_texts()
+                        .find(|t| t.description == description)
+                        .map(|t| t.value.clone());
+                }
+                if let Some(description) = name.strip_prefix("wxxx:") {
+                    return self
+                        .tag
+                        .extended_links()
+                        .find(|l| l.description == description)
+                        .map(|l| l.link.clone());
+                }
+                match self.tag.get(name)?.content() {
+                    Content::Text(text) => Some(text.replace('\0', "/")),
+                    Content::Link(link) => Some(link.clone()),
+                    _ => None,
+                }
+            }
+        }
+    }
 
-            fs::rename(&self.path, &new_path)
-                .map_err(|e| format!("Error renaming file :: {}", e))?;
-            println!("Successfully formatted file :: {:?}", new_path);
-            self.path = new_path;
+    pub fn rename(&mut self, new_path: PathBuf) -> Result<(), String> {
+        if new_path == self.path {
+            return Ok(());
+        }
+        // Renaming over another file would destroy it beyond what --undo can restore
+        if new_path.exists() {
+            return Err(format!(
+                "Error renaming file :: {:?} already exists",
+                new_path
+            ));
         }
+        fs::rename(&self.path, &new_path).map_err(|e| format!("Error renaming file :: {}", e))?;
+        status!("Successfully formatted file :: {:?}", new_path);
+        self.path = new_path;
         Ok(())
     }
+
     pub fn print_details(&self) {
         let mut frames: Vec<_> = self.tag.frames().collect();
         frames.sort_by(|a, b| a.id().cmp(b.id()));
         println!("{}", self.tag.version());
 
         println!(
-            "{0: <8} | {1: <40} | {2: <10}",
-            "Frame ID", "Frame Name", "Frame Content"
+            "{0: <8} | {1: <40} | {2: <20} | {3: <10}",
+            "Frame ID", "Frame Name", "Description", "Frame Content"
         );
-        println!("{:->9}+{:->42}+{:->20}", "", "", "");
+        println!("{:->9}+{:->42}+{:->22}+{:->20}", "", "", "", "");
         for frame in frames {
+            // Legacy references like `(17)` are shown by name
+            let content = match frame.id() {
+                "TCON" => genre::normalized(&self.tag)
+                    .map(|g| g.join(values::SEPARATOR))
+                    .unwrap_or_default(),
+                _ => display_content(frame.content()),
+            };
             println!(
-                "{0: <8} | {1: <40} | {2: <10}",
+                "{0: <8} | {1: <40} | {2: <20} | {3: <10}",
                 frame.id(),
                 frame.name(),
-                frame.content()
+                frame_description(frame.content()),
+                content
             );
         }
         println!("\n");
     }
 
-    pub fn save(&self, version: id3::Version) -> Result<(), id3::Error> {
-        self.tag.write_to_path(&self.path, version)
+    pub fn print_user_frames(&self) {
+        let mut frames: Vec<_> = self
+            .tag
+            .frames()
+            .filter(|f| f.id() == "TXXX" || f.id() == "WXXX")
+            .collect();
+        frames.sort_by(|a, b| {
+            (a.id(), frame_description(a.content())).cmp(&(b.id(), frame_description(b.content())))
+        });
+
+        status!(
+            "{0: <8} | {1: <30} | {2: <10}",
+            "Frame ID",
+            "Description",
+            "Value"
+        );
+        status!("{:->9}+{:->32}+{:->20}", "", "", "");
+        for frame in frames {
+            status!(
+                "{0: <8} | {1: <30} | {2: <10}",
+                frame.id(),
+                frame_description(frame.content()),
+                display_content(frame.content())
+            );
+        }
+        status!("\n");
+    }
+
+    pub fn print_pictures(&self) {
+        status!(
+            "{0: <3} | {1: <20} | {2: <20} | {3: <12} | {4: <11} | {5: <10}",
+            "#",
+            "Type",
+            "Description",
+            "Format",
+            "Size",
+            "Bytes"
+        );
+        status!(
+            "{:->4}+{:->22}+{:->22}+{:->14}+{:->13}+{:->12}",
+            "",
+            "",
+            "",
+            "",
+            "",
+            ""
+        );
+        for (i, p) in self.tag.pictures().enumerate() {
+            let size = cover::dimensions(&p.data)
+                .map(|(w, h)| format!("{}x{}", w, h))
+                .unwrap_or_else(|| "?".to_string());
+            status!(
+                "{0: <3} | {1: <20} | {2: <20} | {3: <12} | {4: <11} | {5: <10}",
+                i + 1,
+                p.picture_type.to_string(),
+                p.description,
+                p.mime_type,
+                size,
+                p.data.len()
+            );
+        }
+        status!("\n");
+    }
+
+    pub fn save(&self, version: id3::Version, backup: &Backup) -> Result<(), String> {
+        storage::backup(&self.path, backup)?;
+        storage::write_atomic(&self.path, &self.tag, version, &self.trailer)
+    }
+
+    /// Saves the ID3v1 tag only, leaving the rest of the file as it is.
+    pub fn save_trailer(&self, backup: &Backup) -> Result<(), String> {
+        storage::backup(&self.path, backup)?;
+        storage::write_trailer(&self.path, &self.trailer)
     }
 
     pub fn apply(
@@ -111,106 +482,574 @@ impl Track {
         version: id3::Version,
     ) -> Result<bool, Box<dyn std::error::Error>> {
         let mut wrote = 0;
-        let total_changes = change_set.actions.len()
+        let parsed = match &change_set.from_filename {
+            Some(pattern) => pattern.actions(&self.path)?,
+            None => Vec::new(),
+        };
+        let total_changes = parsed.len()
+            + change_set.actions.len()
             + change_set.delete_all as usize
-            + change_set.format_file as usize;
+            + change_set.convert as usize
+            + change_set.format_file.is_some() as usize
+            + change_set.organize.is_some() as usize
+            + change_set.id3v1.is_some_and(|m| m != Id3v1Mode::Write) as usize;
 
         let mut modified_file = false;
         let mut printed_details = false;
+        let mut exported_lyrics = false;
+        let mut exported_art = false;
+        let mut exported_lrc = false;
+        let mut listed_user_frames = false;
+        let mut listed_pictures = false;
 
         if change_set.print_details {
-            self.print_details();
+            match change_set.output {
+                OutputFormat::Table => self.print_details(),
+                format => output::emit(
+                    &output::track_json(self, change_set.include_pictures),
+                    format,
+                ),
+            }
             printed_details = true
         }
 
+        if change_set.list_user_frames {
+            self.print_user_frames();
+            listed_user_frames = true
+        }
+
+        if change_set.list_pictures {
+            self.print_pictures();
+            listed_pictures = true
+        }
+
+        if change_set.export_lyrics {
+            self.export_lyrics(change_set.dry_run, change_set.overwrite)?;
+            exported_lyrics = true
+        }
+
+        if let Some(mode) = change_set.extract_art {
+            self.export_art(mode, change_set.dry_run)?;
+            exported_art = true
+        }
+
+        if change_set.export_lrc {
+            self.export_lrc(change_set.dry_run, change_set.overwrite)?;
+            exported_lrc = true
+        }
+
         if change_set.delete_all {
             let tag_clone = self.tag.clone();
             let frames = tag_clone.frames();
             for frame in frames {
                 self.tag.remove(frame.id());
-                println!("Deleted {}, Containing: {}", frame.id(), frame.content());
+                status!("Deleted {}, Containing: {}", frame.id(), frame.content());
             }
             wrote += 1;
         }
-        for action in &change_set.actions {
+        for action in parsed.iter().chain(&change_set.actions) {
             match action {
                 ModifyAction::Title(s) => {
                     self.tag.set_title(s);
-                    println!("Set title successfully: {:?}", s);
+                    status!("Set title successfully: {:?}", s);
                     wrote += 1;
                 }
 
                 ModifyAction::Album(s) => {
                     self.tag.set_album(s);
-                    println!("Set album successfully: {:?}", s);
+                    status!("Set album successfully: {:?}", s);
                     wrote += 1;
                 }
 
-                ModifyAction::Artist(s) => {
-                    self.tag.set_artist(s);
-                    println!("Set artist successfully: {:?}", s);
+                ModifyAction::Artist(artists) => {
+                    self.tag.set_text_values("TPE1", artists);
+                    status!(
+                        "Set artist successfully: {:?}",
+                        artists.join(values::SEPARATOR)
+                    );
                     wrote += 1;
                 }
 
                 ModifyAction::AlbumArtist(s) => {
                     self.tag.set_album_artist(s);
-                    println!("Set album artist successfully: {:?}", s);
+                    status!("Set album artist successfully: {:?}", s);
+                    wrote += 1;
+                }
+
+                ModifyAction::RecordingDate(ts) => {
+                    dates::set_recorded(&mut self.tag, *ts);
+                    status!("Set recording date successfully: {}", ts);
+                    wrote += 1;
+                }
+
+                ModifyAction::OriginalDate(ts) => {
+                    dates::set_original(&mut self.tag, *ts);
+                    status!("Set original release date successfully: {}", ts);
+                    wrote += 1;
+                }
+
+                ModifyAction::ReleaseDate(ts) if version != id3::Version::Id3v24 => {
+                    status!(
+                        "{} has no release date frame, not setting it to {}. Save as ID3v2.4 with --v24",
+                        version,
+                        ts
+                    );
+                }
+
+                ModifyAction::ReleaseDate(ts) => {
+                    self.tag.set_date_released(*ts);
+                    status!("Set release date successfully: {}", ts);
+                    wrote += 1;
+                }
+
+                ModifyAction::Genre(genres) => {
+                    self.tag.set_text_values("TCON", genres);
+                    status!(
+                        "Set genre successfully: {:?}",
+                        genres.join(values::SEPARATOR)
+                    );
                     wrote += 1;
                 }
 
-                ModifyAction::Year(y) => {
-                    if version == id3::Version::Id3v24 {
-                        self.tag.set_date_recorded(Timestamp {
-                            year: *y,
-                            ..Default::default()
-                        });
+                ModifyAction::AddValue { id, value } => {
+                    let mut current = self.text_values(id);
+                    if current.iter().any(|v| same_value(id, v, value)) {
+                        status!("{} already contains {:?}", id, value);
+                        continue;
+                    }
+                    current.push(value.clone());
+                    self.tag.set_text_values(id, current);
+                    status!("Added {:?} to {}", value, id);
+                    wrote += 1;
+                }
+
+                ModifyAction::RemoveValue { id, value } => {
+                    let mut current = self.text_values(id);
+                    let count = current.len();
+                    current.retain(|v| !same_value(id, v, value));
+                    if current.len() == count {
+                        status!("No {:?} found in {}", value, id);
+                        continue;
+                    }
+                    if current.is_empty() {
+                        self.tag.remove(id);
                     } else {
-                        self.tag.set_year(*y);
+                        self.tag.set_text_values(id, current);
                     }
-                    println!("Set year successfully: {}", y);
+                    status!("Removed {:?} from {}", value, id);
                     wrote += 1;
                 }
 
-                ModifyAction::Genre(s) => {
-                    self.tag.set_genre(s);
-                    println!("Set genre successfully: {:?}", s);
+                ModifyAction::SplitValues(separators) => {
+                    for id in values::SPLIT_FRAMES {
+                        let current = self.text_values(id);
+                        let split = values::split(&current, separators);
+                        if split == current {
+                            continue;
+                        }
+                        status!("Split {} into {:?}", id, split.join(values::SEPARATOR));
+                        self.tag.set_text_values(id, split);
+                        wrote += 1;
+                    }
+                }
+
+                ModifyAction::NormalizeGenre => {
+                    let (Some(current), Some(normalized)) =
+                        (self.tag.genres(), genre::normalized(&self.tag))
+                    else {
+                        continue;
+                    };
+                    if current == normalized {
+                        continue;
+                    }
+                    status!(
+                        "Normalized genre {:?} to {:?}",
+                        current.join("/"),
+                        normalized.join("/")
+                    );
+                    self.tag.set_text_values("TCON", normalized);
                     wrote += 1;
                 }
 
                 ModifyAction::TrackNumber(n) => {
                     self.tag.set_track(*n);
-                    println!("Set track number successfully: {:?}", n);
+                    status!("Set track number successfully: {:?}", n);
+                    wrote += 1;
+                }
+
+                ModifyAction::TotalTracks(Some(n)) => {
+                    self.tag.set_total_tracks(*n);
+                    status!("Set total tracks successfully: {:?}", n);
+                    wrote += 1;
+                }
+
+                ModifyAction::TotalTracks(None) => {
+                    self.tag.remove_total_tracks();
+                    status!("Removed total tracks");
+                    wrote += 1;
+                }
+
+                ModifyAction::DiscNumber(n) => {
+                    self.tag.set_disc(*n);
+                    status!("Set disc number successfully: {:?}", n);
+                    wrote += 1;
+                }
+
+                ModifyAction::TotalDiscs(Some(n)) => {
+                    self.tag.set_total_discs(*n);
+                    status!("Set total discs successfully: {:?}", n);
+                    wrote += 1;
+                }
+
+                ModifyAction::TotalDiscs(None) => {
+                    self.tag.remove_total_discs();
+                    status!("Removed total discs");
                     wrote += 1;
                 }
 
                 ModifyAction::DeleteTag(id) => {
                     let removed = self.tag.remove(id);
                     for f in removed {
-                        println!("Deleted: {}, Containing: {}", f.id(), f.content());
+                        status!("Deleted: {}, Containing: {}", f.id(), f.content());
                     }
                     wrote += 1;
                 }
 
                 ModifyAction::CoverArt(p) => {
-                    self.set_cover_art(p)?;
-                    println!("Updated image");
+                    self.set_picture(PictureType::CoverFront, "Cover Art", p, &change_set.cover)?;
+                    status!("Updated image");
+                    wrote += 1;
+                }
+
+                ModifyAction::Picture {
+                    picture_type,
+                    description,
+                    path,
+                } => {
+                    self.set_picture(*picture_type, description, path, &change_set.cover)?;
+                    status!(
+                        "Set picture successfully: {} {:?}",
+                        picture_type,
+                        description
+                    );
+                    wrote += 1;
+                }
+
+                ModifyAction::RemovePictures(selectors) => {
+                    let removed = self.remove_pictures(selectors);
+                    if removed.is_empty() {
+                        status!("No matching pictures found");
+                    }
+                    for p in removed {
+                        status!("Deleted picture: {} {:?}", p.picture_type, p.description);
+                        wrote += 1;
+                    }
+                }
+
+                ModifyAction::Custom(frame) => {
+                    self.tag.add_frame(frame.clone());
+                    status!(
+                        "Set {} successfully: {}",
+                        frame.id(),
+                        display_content(frame.content())
+                    );
+                    wrote += 1;
+                }
+
+                ModifyAction::Comment {
+                    lang,
+                    description,
+                    text,
+                } => {
+                    let replaced = self.tag.add_frame(id3::frame::Comment {
+                        lang: lang.clone(),
+                        description: description.clone(),
+                        text: text.clone(),
+                    });
+                    if replaced.is_some() {
+                        status!("Replaced comment [{}] {:?}: {:?}", lang, description, text);
+                    } else {
+                        status!("Added comment [{}] {:?}: {:?}", lang, description, text);
+                    }
+                    wrote += 1;
+                }
+
+                ModifyAction::RemoveComment { lang, description } => {
+                    let removed = self.remove_frames_where("COMM", |f| {
+                        f.content().comment().is_some_and(|c| {
+                            c.description == *description
+                                && lang.as_ref().is_none_or(|l| c.lang == *l)
+                        })
+                    });
+                    if removed.is_empty() {
+                        status!("No comment found with description {:?}", description);
+                    }
+                    for f in removed {
+                        status!(
+                            "Deleted: {} {:?}, Containing: {}",
+                            f.id(),
+                            description,
+                            display_content(f.content())
+                        );
+                        wrote += 1;
+                    }
+                }
+
+                ModifyAction::UserText(description, value) => {
+                    self.tag.add_frame(ExtendedText {
+                        description: description.clone(),
+                        value: value.clone(),
+                    });
+                    status!("Set TXXX {:?} successfully: {:?}", description, value);
+                    wrote += 1;
+                }
+
+                ModifyAction::UserLink(description, link) => {
+                    self.tag.add_frame(ExtendedLink {
+                        description: description.clone(),
+                        link: link.clone(),
+                    });
+                    status!("Set WXXX {:?} successfully: {:?}", description, link);
                     wrote += 1;
                 }
 
-                ModifyAction::Custom(tag, content) => {
-                    self.tag.set_text(tag, content);
+                ModifyAction::RemoveUserFrame(id, description) => {
+                    let removed = self
+                        .remove_frames_where(id, |f| frame_description(f.content()) == description);
+                    if removed.is_empty() {
+                        status!("No {} found with description {:?}", id, description);
+                    }
+                    for f in removed {
+                        status!(
+                            "Deleted: {} {:?}, Containing: {}",
+                            f.id(),
+                            description,
+                            display_content(f.content())
+                        );
+                        wrote += 1;
+                    }
+                }
+
+                ModifyAction::Lyrics {
+                    lang,
+                    description,
+                    text,
+                } => {
+                    self.tag.add_frame(id3::frame::Lyrics {
+                        lang: lang.clone(),
+                        description: description.clone(),
+                        text: text.clone(),
+                    });
+                    status!(
+                        "Set lyrics [{}] {:?} successfully: {} lines",
+                        lang,
+                        description,
+                        text.lines().count()
+                    );
+                    wrote += 1;
+                }
+
+                ModifyAction::SyncedLyrics {
+                    lang,
+                    description,
+                    content,
+                } => {
+                    self.set_synced_lyrics(lang, description, content.clone());
+                    wrote += 1;
+                }
+
+                ModifyAction::AutoLrc { lang, description } => {
+                    let lrc_path = self.path.with_extension("lrc");
+                    if !lrc_path.is_file() {
+                        status!("No LRC file found at {:?}", lrc_path);
+                        continue;
+                    }
+                    let text = fs::read_to_string(&lrc_path)
+                        .map_err(|e| format!("Error reading LRC file {:?} :: {}", lrc_path, e))?;
+                    let content = lyrics::parse_lrc(&text)
+                        .map_err(|e| format!("Error parsing {:?} :: {}", lrc_path, e))?;
+                    self.set_synced_lyrics(lang, description, content);
                     wrote += 1;
                 }
             }
         }
 
-        if total_changes == 0 && !printed_details {
+        if change_set.convert {
+            for frame in convert::convert(&mut self.tag, version, &change_set.separator) {
+                status!(
+                    "Not supported by {}, removed {}: {}",
+                    version,
+                    frame.id(),
+                    display_content(frame.content())
+                );
+            }
+            status!("Converted tag to {}", version);
+            wrote += 1;
+        }
+
+        if total_changes == 0
+            && !printed_details
+            && !listed_user_frames
+            && !listed_pictures
+            && !exported_lyrics
+            && !exported_art
+            && !exported_lrc
+        {
             return Err(Box::from("Missing arguments, use 'editag --help' for help"));
         }
 
         if wrote > 0 {
+            dates::normalize(&mut self.tag, version);
+            if version != id3::Version::Id3v24 {
+                convert::join_values(&mut self.tag, &change_set.separator);
+            } else if self.original_version.is_some_and(|v| v != version) {
+                convert::rejoin_values(&mut self.tag);
+            }
             modified_file = true;
         }
+
+        match change_set.id3v1 {
+            Some(Id3v1Mode::Strip) if self.id3v1.is_some() => {
+                self.trailer = Trailer::Strip;
+                if self.original_version.is_none() && !modified_file {
+                    status!("No ID3v2 tag found, only removing the ID3v1 tag");
+                } else {
+                    status!("Removed ID3v1 tag");
+                }
+                modified_file = true;
+            }
+            Some(Id3v1Mode::Strip) => status!("No ID3v1 tag to remove"),
+            Some(mode) if modified_file || mode == Id3v1Mode::Sync => {
+                let (data, warnings) = id3v1::encode(self);
+                let old = self.id3v1.as_deref();
+                if !old.is_some_and(|o| id3v1::up_to_date(o, self, &data)) {
+                    for warning in warnings {
+                        status!("{}", warning);
+                    }
+                    if old.is_some_and(|o| o.len() > id3v1::TAG_LEN) {
+                        status!("Removed the extended ID3v1 tag (TAG+)");
+                    }
+                    self.trailer = Trailer::Write(data);
+                    status!("Set ID3v1 tag successfully");
+                    modified_file = true;
+                } else if !modified_file {
+                    status!("ID3v1 tag already up to date");
+                }
+            }
+            _ => {}
+        }
         Ok(modified_file)
     }
 }
+
+/// Whether a value of the frame is the given one, ignoring case. Genres are compared by name, so
+/// `(17)` matches `Rock`.
+fn same_value(id: &str, current: &str, value: &str) -> bool {
+    if id == "TCON" {
+        genre::decode(current).eq_ignore_ascii_case(&genre::canonical(value))
+    } else {
+        current.trim().eq_ignore_ascii_case(value.trim())
+    }
+}
+
+/// Formats frame content for the details table, adding the fields `Display` leaves out.
+/// The description is left to its own column, see [`frame_description`].
+pub fn display_content(content: &Content) -> String {
+    match content {
+        Content::Text(t) => t.replace('\0', values::SEPARATOR),
+        Content::ExtendedText(t) => t.value.clone(),
+        Content::ExtendedLink(l) => l.link.clone(),
+        Content::Comment(c) => format!("[{}] {}", c.lang, c.text),
+        Content::Lyrics(l) => format!("[{}] {} lines", l.lang, l.text.lines().count()),
+        Content::SynchronisedLyrics(l) => format!(
+            "[{}] {} lines ({})",
+            l.lang,
+            l.content.len(),
+            l.content_type
+        ),
+        Content::Popularimeter(p) => format!("*{}* ({})", p.rating, p.counter),
+        Content::Picture(p) => format!(
+            "{} ({}, {} bytes)",
+            p.picture_type,
+            p.mime_type,
+            p.data.len()
+        ),
+        _ => content.to_string(),
+    }
+}
+
+/// The description (or owner) that tells apart several frames sharing an ID.
+pub fn frame_description(content: &Content) -> &str {
+    match content {
+        Content::ExtendedText(t) => &t.description,
+        Content::ExtendedLink(l) => &l.description,
+        Content::Comment(c) => &c.description,
+        Content::Lyrics(l) => &l.description,
+        Content::SynchronisedLyrics(l) => &l.description,
+        Content::Picture(p) => &p.description,
+        Content::EncapsulatedObject(o) => &o.description,
+        Content::Popularimeter(p) => &p.user,
+        Content::UniqueFileIdentifier(u) => &u.owner_identifier,
+        Content::Private(p) => &p.owner_identifier,
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picture(picture_type: PictureType, description: &str) -> Picture {
+        Picture {
+            mime_type: "image/png".to_string(),
+            picture_type,
+            description: description.to_string(),
+            data: description.as_bytes().to_vec(),
+        }
+    }
+
+    fn track(pictures: Vec<Picture>) -> Track {
+        Track {
+            tag: pictures.into_iter().map(Frame::from).collect(),
+            path: PathBuf::from("test.mp3"),
+            original_version: None,
+            id3v1: None,
+            trailer: Trailer::Keep,
+        }
+    }
+
+    fn descriptions(track: &Track) -> Vec<&str> {
+        track
+            .tag
+            .pictures()
+            .map(|p| p.description.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn remove_pictures_by_number_keeps_others_of_the_same_type() {
+        let mut track = track(vec![
+            picture(PictureType::Other, "page 1"),
+            picture(PictureType::CoverFront, "front"),
+            picture(PictureType::Other, "page 2"),
+            picture(PictureType::Other, "page 3"),
+        ]);
+        let removed = track.remove_pictures(&[PictureSelector::Index(3)]);
+
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].description, "page 2");
+        assert_eq!(descriptions(&track), ["page 1", "front", "page 3"]);
+    }
+
+    #[test]
+    fn remove_pictures_by_type() {
+        let mut track = track(vec![
+            picture(PictureType::Other, "page 1"),
+            picture(PictureType::CoverFront, "front"),
+            picture(PictureType::Other, "page 2"),
+        ]);
+        track.remove_pictures(&[PictureSelector::Type(PictureType::Other)]);
+
+        assert_eq!(descriptions(&track), ["front"]);
+    }
+}
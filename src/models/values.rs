@@ -0,0 +1,43 @@
+use regex::{Regex, RegexBuilder};
+
+/// Joins the values of artists and genres in tables, file names and CSV cells.
+pub const SEPARATOR: &str = "; ";
+
+/// Frames `--split` applies to.
+pub const SPLIT_FRAMES: [&str; 2] = ["TPE1", "TCON"];
+
+/// Splits a cell or manifest string written with [`SEPARATOR`] back into its values.
+pub fn from_cell(cell: &str) -> Vec<String> {
+    cell.split(SEPARATOR.trim())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Splits every value on any of the separators, ignoring case, dropping empty and repeated
+/// values.
+pub fn split(values: &[String], separators: &[String]) -> Vec<String> {
+    let Some(pattern) = pattern(separators) else {
+        return values.to_vec();
+    };
+    let mut result: Vec<String> = Vec::new();
+    for part in values.iter().flat_map(|v| pattern.split(v)) {
+        let part = part.trim();
+        if !part.is_empty() && !result.iter().any(|r| r == part) {
+            result.push(part.to_string());
+        }
+    }
+    result
+}
+
+fn pattern(separators: &[String]) -> Option<Regex> {
+    if separators.is_empty() {
+        return None;
+    }
+    let alternatives: Vec<_> = separators.iter().map(|s| regex::escape(s)).collect();
+    RegexBuilder::new(&alternatives.join("|"))
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
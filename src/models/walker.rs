@@ -0,0 +1,188 @@
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::models::controls::AppConfig;
+
+pub struct Walker<'a> {
+    root: &'a Path,
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    include_hidden: bool,
+    include: &'a [Pattern],
+    exclude: &'a [Pattern],
+    visited: HashSet<PathBuf>,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(config: &'a AppConfig) -> Self {
+        Walker {
+            root: &config.target_path,
+            max_depth: config.max_depth,
+            follow_symlinks: config.follow_symlinks,
+            include_hidden: config.include_hidden,
+            include: &config.include,
+            exclude: &config.exclude,
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Collects every mp3 below the root, sorted by path so batch runs are deterministic.
+    pub fn collect(mut self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let root = self.root.to_path_buf();
+        if let Ok(canonical) = fs::canonicalize(&root) {
+            self.visited.insert(canonical);
+        }
+        self.walk(&root, 0, &mut files);
+        files
+    }
+
+    fn walk(&mut self, dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+        let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+            Err(e) => {
+                eprintln!("Error reading directory {:?} :: {}", dir, e);
+                return;
+            }
+        };
+        entries.sort();
+
+        for path in entries {
+            if !self.include_hidden && is_hidden(&path) {
+                continue;
+            }
+            if self.is_excluded(&path) {
+                continue;
+            }
+
+            let is_symlink = fs::symlink_metadata(&path)
+                .map(|m| m.file_type().is_symlink())
+                .unwrap_or(false);
+            if is_symlink && !self.follow_symlinks {
+                continue;
+            }
+
+            if path.is_dir() {
+                if self.max_depth.is_some_and(|max| depth >= max) {
+                    continue;
+                }
+                // Guards against symlink loops and directories reachable twice
+                if let Ok(canonical) = fs::canonicalize(&path) {
+                    if !self.visited.insert(canonical) {
+                        continue;
+                    }
+                }
+                self.walk(&path, depth + 1, files);
+            } else if is_mp3(&path) && self.is_included(&path) {
+                // Followed symlinks may lead to a file found elsewhere in the tree
+                if self.follow_symlinks {
+                    if let Ok(canonical) = fs::canonicalize(&path) {
+                        if !self.visited.insert(canonical) {
+                            continue;
+                        }
+                    }
+                }
+                files.push(path);
+            }
+        }
+    }
+
+    fn relative<'p>(&self, path: &'p Path) -> &'p Path {
+        path.strip_prefix(self.root).unwrap_or(path)
+    }
+
+    fn matches(&self, pattern: &Pattern, path: &Path) -> bool {
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        // Patterns without a separator match the file name anywhere in the tree
+        if !pattern.as_str().contains('/') {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                return pattern.matches_with(name, options);
+            }
+        }
+        pattern.matches_path_with(self.relative(path), options)
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude.iter().any(|p| self.matches(p, path))
+    }
+
+    fn is_included(&self, path: &Path) -> bool {
+        self.include.is_empty() || self.include.iter().any(|p| self.matches(p, path))
+    }
+}
+
+pub fn is_mp3(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("mp3"))
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.'))
+}
+
+/// Compares file names the way people read them, with runs of digits by their value, so
+/// `2 x.mp3` comes before `10 x.mp3`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    loop {
+        let (a_part, a_next) = leading_run(a_rest);
+        let (b_part, b_next) = leading_run(b_rest);
+        if a_part.is_empty() || b_part.is_empty() {
+            return a_part.len().cmp(&b_part.len()).then_with(|| a.cmp(b));
+        }
+        let a_digits = a_part.starts_with(|c: char| c.is_ascii_digit());
+        let b_digits = b_part.starts_with(|c: char| c.is_ascii_digit());
+        let order = if a_digits && b_digits {
+            let (a_num, b_num) = (
+                a_part.trim_start_matches('0'),
+                b_part.trim_start_matches('0'),
+            );
+            a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num))
+        } else {
+            a_part.cmp(b_part)
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+        (a_rest, b_rest) = (a_next, b_next);
+    }
+}
+
+/// Splits off the leading run of digits, or of anything but digits.
+fn leading_run(s: &str) -> (&str, &str) {
+    let digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digits)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        let mut names = [
+            "10 x.mp3", "2 y.mp3", "1 z.mp3", "02 a.mp3", "b.mp3", "a.mp3",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            ["1 z.mp3", "02 a.mp3", "2 y.mp3", "10 x.mp3", "a.mp3", "b.mp3"]
+        );
+    }
+}